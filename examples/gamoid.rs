@@ -1,12 +1,15 @@
-use std::process;
-
-use windows_sys::Win32::Graphics::OpenGL::{
-    glClear, glClearColor, glColorPointer, glCullFace, glDrawArrays, glEnableClientState,
-    glFrontFace, glLoadIdentity, glMatrixMode, glVertexPointer, glViewport, gluLookAt,
-    gluPerspective, SwapBuffers, GL_BACK, GL_CCW, GL_COLOR_ARRAY, GL_COLOR_BUFFER_BIT, GL_FLOAT,
-    GL_MODELVIEW, GL_PROJECTION, GL_TRIANGLES, GL_VERTEX_ARRAY,
+use std::{ffi::CString, process};
+
+use windows_sys::Win32::{
+    Graphics::OpenGL::{
+        glClear, glClearColor, glColorPointer, glCullFace, glDrawArrays, glEnableClientState,
+        glFrontFace, glLoadIdentity, glMatrixMode, glVertexPointer, glViewport, gluLookAt,
+        gluPerspective, GL_BACK, GL_CCW, GL_COLOR_ARRAY, GL_COLOR_BUFFER_BIT, GL_FLOAT,
+        GL_MODELVIEW, GL_PROJECTION, GL_TRIANGLES, GL_VERTEX_ARRAY,
+    },
+    UI::WindowsAndMessaging::{MessageBoxA, MB_ICONERROR},
 };
-use xenocore as xc;
+use xenocore::{self as xc, Backend};
 
 #[rustfmt::skip]
 const VERTICES: &[[f32; 3]] = &[
@@ -23,11 +26,20 @@ const COLORS: &[[f32; 3]] = &[
 ];
 
 fn main() {
-    let window = xc::win32::Window::new(c"xenocore-gamoid");
+    // Panics abort silently in optimized builds, a message box is all the player gets to see.
+    let window = match xc::Window::new(c"xenocore-gamoid") {
+        Ok(window) => window,
+
+        Err(error) => {
+            let text = CString::new(error.to_string()).unwrap();
+            let caption = c"xenocore-gamoid".as_ptr() as _;
+
+            unsafe { MessageBoxA(0, text.as_ptr() as _, caption, MB_ICONERROR) };
+            process::exit(1);
+        }
+    };
 
-    let mut vsync = unsafe { xc::wgl_load!(c"wglGetSwapIntervalEXT", fn() -> isize) }
-        .map(|proc| unsafe { proc() } != 0)
-        .unwrap_or_default();
+    let mut fullscreen = false;
 
     unsafe {
         glClearColor(0., 0., 0., 1.);
@@ -37,18 +49,50 @@ fn main() {
         glCullFace(GL_BACK);
     };
 
-    window.event_loop(|event| {
+    window.event_loop(|_, event| {
         match event {
-            xc::Event::Press(xc::key::ESCAPE | xc::key::Q) => {
-                process::exit(0);
+            xc::Event::CloseRequested => {
+                window.close();
+                return;
             }
 
-            xc::Event::Press(xc::key::V) => {
-                unsafe { toggle_vsync(&mut vsync) };
+            xc::Event::Press {
+                key: xc::Key::Escape | xc::Key::Q,
+                ..
+            } => {
+                // Returns from the loop, so the window is dropped properly at the end of `main`.
+                window.set_control_flow(xc::ControlFlow::Exit);
                 return;
             }
 
-            _ => {}
+            xc::Event::Press {
+                key: xc::Key::Return,
+                repeat: false,
+                modifiers,
+                ..
+            } if modifiers.contains(xc::Modifiers::ALT) => {
+                fullscreen = !fullscreen;
+                window.set_fullscreen(fullscreen.then(|| window.current_monitor()));
+                return;
+            }
+
+            xc::Event::Press {
+                key: xc::Key::V,
+                repeat: false,
+                ..
+            } => {
+                let vsync = match window.vsync() {
+                    Some(xc::Vsync::Off) => xc::Vsync::On,
+                    _ => xc::Vsync::Off,
+                };
+
+                window.set_vsync(vsync);
+                return;
+            }
+
+            // Draws once the pending events are handled, and never for a window that's gone.
+            xc::Event::Idle => {}
+            _ => return,
         }
 
         let [width, height] = window.inner_size();
@@ -71,16 +115,7 @@ fn main() {
             glColorPointer(3, GL_FLOAT, 0, COLORS.as_ptr() as _);
             glDrawArrays(GL_TRIANGLES, 0, 3);
 
-            SwapBuffers(window.hdc);
+            window.swap_buffers();
         }
     });
 }
-
-unsafe fn toggle_vsync(vsync: &mut bool) {
-    *vsync ^= true;
-
-    #[allow(non_snake_case)]
-    if let Some(wglSwapIntervalEXT) = xc::wgl_load!(c"wglSwapIntervalEXT", fn(isize) -> isize) {
-        wglSwapIntervalEXT(*vsync as _);
-    }
-}
@@ -1,9 +1,8 @@
 use windows_sys::Win32::Graphics::OpenGL::{
     glClear, glClearColor, glColorPointer, glDrawArrays, glEnableClientState, glVertexPointer,
-    glViewport, SwapBuffers, GL_COLOR_ARRAY, GL_COLOR_BUFFER_BIT, GL_FLOAT, GL_TRIANGLES,
-    GL_VERTEX_ARRAY,
+    glViewport, GL_COLOR_ARRAY, GL_COLOR_BUFFER_BIT, GL_FLOAT, GL_TRIANGLES, GL_VERTEX_ARRAY,
 };
-use xenocore as xc;
+use xenocore::{self as xc, Backend};
 
 #[rustfmt::skip]
 const VERTICES: &[[f32; 3]] = &[
@@ -20,7 +19,7 @@ const COLORS: &[[f32; 3]] = &[
 ];
 
 fn main() {
-    let window = xc::win32::Window::new(c"xenocore-gl-triangle-arrays");
+    let window = xc::Window::new(c"xenocore-gl-triangle-arrays").unwrap();
 
     unsafe {
         glClearColor(0., 0., 0., 1.);
@@ -28,9 +27,15 @@ fn main() {
         glEnableClientState(GL_COLOR_ARRAY);
     };
 
-    window.event_loop(|event| {
-        if let xc::Event::Press(_) = event {
-            return;
+    window.event_loop(|_, event| {
+        match event {
+            xc::Event::CloseRequested => {
+                window.close();
+                return;
+            }
+
+            xc::Event::Idle => {}
+            _ => return,
         }
 
         let [width, height] = window.inner_size();
@@ -44,7 +49,7 @@ fn main() {
             glColorPointer(3, GL_FLOAT, 0, COLORS.as_ptr() as _);
             glDrawArrays(GL_TRIANGLES, 0, 3);
 
-            SwapBuffers(window.hdc);
+            window.swap_buffers();
         }
     });
 }
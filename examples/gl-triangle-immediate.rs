@@ -1,22 +1,32 @@
 use windows_sys::Win32::Graphics::OpenGL::{
-    glBegin, glClear, glClearColor, glColor3f, glEnd, glVertex3f, glViewport, wglCreateContext,
-    wglDeleteContext, wglMakeCurrent, SwapBuffers, GL_COLOR_BUFFER_BIT, GL_TRIANGLES,
+    glBegin, glClear, glClearColor, glColor3f, glEnd, glVertex3f, glViewport, GL_COLOR_BUFFER_BIT,
+    GL_TRIANGLES,
 };
-use xenocore as xc;
+use xenocore::{self as xc, Backend};
 
 fn main() {
-    let window = xc::win32::Window::new(c"xenocore-gl-triangle-immediate");
+    let window: xc::Window = xc::WindowBuilder::new(c"xenocore-gl-triangle-immediate")
+        .gl_context(false)
+        .build()
+        .unwrap();
 
-    let hglrc = unsafe { wglCreateContext(window.hdc) };
-    assert!(hglrc != 0);
+    // Dropped before the window, as declared after it.
+    let context = xc::GlContext::new(&window, None).unwrap();
+    assert!(context.make_current(&window));
 
     unsafe {
         glClearColor(0., 0., 0., 1.);
     };
 
-    window.event_loop(|event| {
-        if let xc::Event::Press(_) = event {
-            return;
+    window.event_loop(|_, event| {
+        match event {
+            xc::Event::CloseRequested => {
+                window.close();
+                return;
+            }
+
+            xc::Event::Idle => {}
+            _ => return,
         }
 
         let [width, height] = window.inner_size();
@@ -36,12 +46,7 @@ fn main() {
 
             glEnd();
 
-            SwapBuffers(window.hdc);
+            window.swap_buffers();
         }
     });
-
-    unsafe {
-        wglMakeCurrent(0, 0);
-        wglDeleteContext(hglrc);
-    }
 }
@@ -0,0 +1,219 @@
+use core::{
+    cell::{Cell, RefCell, RefMut},
+    iter,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use std::collections::VecDeque;
+
+#[cfg(feature = "gl")]
+use core::{
+    ffi::{c_void, CStr},
+    ptr,
+};
+
+use crate::{Backend, ControlFlow, Error, Event, WindowBuilder, WindowId};
+
+#[cfg(feature = "gl")]
+use crate::{GlConfig, PresentHook, PresentHooks, Vsync};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A window without a display, fed from a scripted queue of events.
+///
+/// `event_loop` and `poll_events` hand out queued events in order and stop once the queue runs dry
+/// or the window is closed, so tests script `Event::Idle` wherever they want a frame to be rendered.
+/// Each window has a queue of its own, and its loop reports nothing else.
+///
+/// Rendering is software-only: there is no GL driver behind it even with the `gl` feature, so
+/// `proc_address` finds nothing and the framebuffer holds whatever the caller writes into it.
+pub struct Window {
+    id: WindowId,
+    size: Cell<[u32; 2]>,
+    closed: Cell<bool>,
+    destroyed: Cell<bool>,
+    key_repeat: Cell<bool>,
+    control_flow: Cell<ControlFlow>,
+    events: RefCell<VecDeque<Event>>,
+    framebuffer: RefCell<Vec<[u8; 4]>>,
+
+    #[cfg(feature = "gl")]
+    frames: Cell<usize>,
+
+    #[cfg(feature = "gl")]
+    pixel_format: GlConfig,
+
+    #[cfg(feature = "gl")]
+    vsync: Cell<Vsync>,
+
+    #[cfg(feature = "gl")]
+    present_hooks: PresentHooks<Self>,
+}
+
+impl Window {
+    pub fn push(&self, event: Event) {
+        self.events.borrow_mut().push_back(event);
+    }
+
+    /// Resizes the framebuffer and queues the matching `Event::Resize`, as if the user did it.
+    pub fn resize(&self, size: [u32; 2]) {
+        self.set_size(size);
+        self.push(Event::Resize(size));
+    }
+
+    /// RGBA pixels in row-major order, top row first.
+    pub fn framebuffer(&self) -> RefMut<'_, [[u8; 4]]> {
+        RefMut::map(self.framebuffer.borrow_mut(), Vec::as_mut_slice)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.get()
+    }
+
+    /// Only `Exit` is acted upon, the scripted queue decides when `Idle` comes.
+    pub fn control_flow(&self) -> ControlFlow {
+        self.control_flow.get()
+    }
+
+    /// How many times `swap_buffers` has been called.
+    #[cfg(feature = "gl")]
+    pub fn frames(&self) -> usize {
+        self.frames.get()
+    }
+
+    fn next_event(&self) -> Option<Event> {
+        loop {
+            if self.closed.get() {
+                return (!self.destroyed.replace(true)).then_some(Event::Destroyed);
+            }
+
+            // The borrow ends here, whoever gets the event may well push more.
+            let event = self.events.borrow_mut().pop_front()?;
+
+            match event {
+                Event::Press { repeat: true, .. } if !self.key_repeat.get() => continue,
+                event => return Some(event),
+            }
+        }
+    }
+
+    fn set_size(&self, size: [u32; 2]) {
+        let [width, height] = size;
+
+        self.size.set(size);
+        self.framebuffer
+            .borrow_mut()
+            .resize(width as usize * height as usize, [0; 4]);
+    }
+}
+
+impl Backend for Window {
+    fn build(builder: &WindowBuilder) -> Result<Self, Error> {
+        let window = Self {
+            id: WindowId(NEXT_ID.fetch_add(1, Ordering::Relaxed)),
+            size: Cell::new([0; 2]),
+            closed: Cell::new(false),
+            destroyed: Cell::new(false),
+            key_repeat: Cell::new(true),
+            control_flow: Cell::new(ControlFlow::Poll),
+            events: RefCell::new(VecDeque::new()),
+            framebuffer: RefCell::new(Vec::new()),
+
+            #[cfg(feature = "gl")]
+            frames: Cell::new(0),
+
+            // Any format will do without a driver to turn it down.
+            #[cfg(feature = "gl")]
+            pixel_format: builder.gl_configs.first().copied().unwrap_or_default(),
+
+            #[cfg(feature = "gl")]
+            vsync: Cell::new(Vsync::On),
+
+            #[cfg(feature = "gl")]
+            present_hooks: PresentHooks::new(),
+        };
+
+        window.set_size(builder.inner_size.unwrap_or([640, 480]));
+        Ok(window)
+    }
+
+    fn id(&self) -> WindowId {
+        self.id
+    }
+
+    fn inner_size(&self) -> [u32; 2] {
+        match self.closed.get() {
+            true => [0, 0],
+            false => self.size.get(),
+        }
+    }
+
+    fn event_loop(&self, mut cb: impl FnMut(WindowId, Event)) {
+        while let Some(event) = self.next_event() {
+            cb(self.id, event);
+
+            if self.control_flow.get() == ControlFlow::Exit {
+                self.control_flow.set(ControlFlow::Poll);
+                return;
+            }
+        }
+    }
+
+    fn poll_events(&self) -> impl Iterator<Item = Event> + '_ {
+        iter::from_fn(|| self.next_event())
+    }
+
+    // Nothing else would ever fill the queue, so there's no point in waiting.
+    fn wait_events(&self) -> impl Iterator<Item = Event> + '_ {
+        self.poll_events()
+    }
+
+    fn close(&self) {
+        self.closed.set(true);
+    }
+
+    fn set_control_flow(&self, flow: ControlFlow) {
+        self.control_flow.set(flow);
+    }
+
+    fn set_key_repeat(&self, enabled: bool) {
+        self.key_repeat.set(enabled);
+    }
+
+    #[cfg(feature = "gl")]
+    fn swap_buffers(&self) {
+        self.present_hooks.present(self, || {
+            self.frames.set(self.frames.get() + 1);
+        });
+    }
+
+    #[cfg(feature = "gl")]
+    fn set_before_present(&self, hook: Option<PresentHook<Self>>) {
+        self.present_hooks.set_before(hook);
+    }
+
+    #[cfg(feature = "gl")]
+    fn set_after_present(&self, hook: Option<PresentHook<Self>>) {
+        self.present_hooks.set_after(hook);
+    }
+
+    #[cfg(feature = "gl")]
+    fn set_vsync(&self, vsync: Vsync) -> bool {
+        self.vsync.set(vsync);
+        true
+    }
+
+    #[cfg(feature = "gl")]
+    fn vsync(&self) -> Option<Vsync> {
+        Some(self.vsync.get())
+    }
+
+    #[cfg(feature = "gl")]
+    fn pixel_format(&self) -> GlConfig {
+        self.pixel_format
+    }
+
+    #[cfg(feature = "gl")]
+    fn proc_address(&self, _name: &CStr) -> *const c_void {
+        ptr::null()
+    }
+}
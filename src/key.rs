@@ -0,0 +1,270 @@
+use core::{fmt, ops, str::FromStr};
+
+macro_rules! keys {
+    ($($key:ident),* $(,)?) => {
+        /// A key on the keyboard, independent of the platform reporting it.
+        ///
+        /// Keys without a variant are reported as `Unknown` with the platform's own code.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum Key {
+            $($key,)*
+            Unknown(u32),
+        }
+
+        const NAMES: &[(Key, &str)] = &[$((Key::$key, stringify!($key)),)*];
+    };
+}
+
+keys! {
+    Backspace, Tab, Clear, Return, Pause, CapsLock, Escape, Space,
+    PageUp, PageDown, End, Home, Left, Up, Right, Down, Select, Print, Execute, PrintScreen,
+    Insert, Delete, Help,
+
+    Digit0, Digit1, Digit2, Digit3, Digit4, Digit5, Digit6, Digit7, Digit8, Digit9,
+
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+
+    LSuper, RSuper, Menu, Sleep,
+
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    NumpadMultiply, NumpadAdd, NumpadSeparator, NumpadSubtract, NumpadDecimal, NumpadDivide,
+    NumpadEnter,
+
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    F13, F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24,
+
+    NumLock, ScrollLock, LShift, RShift, LControl, RControl, LAlt, RAlt,
+
+    Semicolon, Equal, Comma, Minus, Period, Slash, Grave, LeftBracket, Backslash, RightBracket,
+    Apostrophe, NonUsBackslash,
+
+    BrowserBack, BrowserForward, BrowserRefresh, BrowserStop, BrowserSearch, BrowserFavorites,
+    BrowserHome, VolumeMute, VolumeDown, VolumeUp, MediaNext, MediaPrevious, MediaStop,
+    MediaPlayPause, LaunchMail, LaunchMediaSelect, LaunchApp1, LaunchApp2,
+
+    Kana, Kanji, Convert, NonConvert,
+}
+
+impl Key {
+    /// The variant name, as accepted by `from_name` and `FromStr`.
+    pub fn name(self) -> Option<&'static str> {
+        NAMES
+            .iter()
+            .find(|&&(key, _)| key == self)
+            .map(|&(_, name)| name)
+    }
+
+    /// Looks a key up by variant name, ignoring ASCII case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        NAMES
+            .iter()
+            .find(|(_, candidate)| candidate.eq_ignore_ascii_case(name))
+            .map(|&(key, _)| key)
+    }
+
+    /// The key found at a scancode's position on a US layout, regardless of the active one.
+    ///
+    /// Scancodes follow the Windows convention: scan code set 1 make codes, with `extended` set for
+    /// the `E0`-prefixed ones, except Num Lock which is reported extended and Pause which isn't.
+    pub fn from_scancode(scancode: u32, extended: bool) -> Self {
+        #[rustfmt::skip]
+        const MAIN: [Key; 0x59] = [
+            Key::Unknown(0x00), Key::Escape, Key::Digit1, Key::Digit2, Key::Digit3, Key::Digit4,
+            Key::Digit5, Key::Digit6, Key::Digit7, Key::Digit8, Key::Digit9, Key::Digit0,
+            Key::Minus, Key::Equal, Key::Backspace, Key::Tab, Key::Q, Key::W, Key::E, Key::R,
+            Key::T, Key::Y, Key::U, Key::I, Key::O, Key::P, Key::LeftBracket, Key::RightBracket,
+            Key::Return, Key::LControl, Key::A, Key::S, Key::D, Key::F, Key::G, Key::H, Key::J,
+            Key::K, Key::L, Key::Semicolon, Key::Apostrophe, Key::Grave, Key::LShift,
+            Key::Backslash, Key::Z, Key::X, Key::C, Key::V, Key::B, Key::N, Key::M, Key::Comma,
+            Key::Period, Key::Slash, Key::RShift, Key::NumpadMultiply, Key::LAlt, Key::Space,
+            Key::CapsLock, Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8,
+            Key::F9, Key::F10, Key::Pause, Key::ScrollLock, Key::Numpad7, Key::Numpad8,
+            Key::Numpad9, Key::NumpadSubtract, Key::Numpad4, Key::Numpad5, Key::Numpad6,
+            Key::NumpadAdd, Key::Numpad1, Key::Numpad2, Key::Numpad3, Key::Numpad0,
+            Key::NumpadDecimal, Key::Unknown(0x54), Key::Unknown(0x55), Key::NonUsBackslash,
+            Key::F11, Key::F12,
+        ];
+
+        #[rustfmt::skip]
+        const HIGH_FUNCTION: [Key; 11] = [
+            Key::F13, Key::F14, Key::F15, Key::F16, Key::F17, Key::F18, Key::F19, Key::F20,
+            Key::F21, Key::F22, Key::F23,
+        ];
+
+        match (scancode, extended) {
+            (0x00..=0x58, false) => MAIN[scancode as usize],
+            (0x64..=0x6E, false) => HIGH_FUNCTION[(scancode - 0x64) as usize],
+            (0x76, false) => Key::F24,
+            (0x70, false) => Key::Kana,
+            (0x79, false) => Key::Convert,
+            (0x7B, false) => Key::NonConvert,
+            (0x10, true) => Key::MediaPrevious,
+            (0x19, true) => Key::MediaNext,
+            (0x1C, true) => Key::NumpadEnter,
+            (0x1D, true) => Key::RControl,
+            (0x20, true) => Key::VolumeMute,
+            (0x22, true) => Key::MediaPlayPause,
+            (0x24, true) => Key::MediaStop,
+            (0x2E, true) => Key::VolumeDown,
+            (0x30, true) => Key::VolumeUp,
+            (0x32, true) => Key::BrowserHome,
+            (0x35, true) => Key::NumpadDivide,
+            (0x37, true) => Key::PrintScreen,
+            (0x38, true) => Key::RAlt,
+            (0x45, true) => Key::NumLock,
+            (0x47, true) => Key::Home,
+            (0x48, true) => Key::Up,
+            (0x49, true) => Key::PageUp,
+            (0x4B, true) => Key::Left,
+            (0x4D, true) => Key::Right,
+            (0x4F, true) => Key::End,
+            (0x50, true) => Key::Down,
+            (0x51, true) => Key::PageDown,
+            (0x52, true) => Key::Insert,
+            (0x53, true) => Key::Delete,
+            (0x5B, true) => Key::LSuper,
+            (0x5C, true) => Key::RSuper,
+            (0x5D, true) => Key::Menu,
+            (0x5F, true) => Key::Sleep,
+            (0x65, true) => Key::BrowserSearch,
+            (0x66, true) => Key::BrowserFavorites,
+            (0x67, true) => Key::BrowserRefresh,
+            (0x68, true) => Key::BrowserStop,
+            (0x69, true) => Key::BrowserForward,
+            (0x6A, true) => Key::BrowserBack,
+            (0x6C, true) => Key::LaunchMail,
+            (0x6D, true) => Key::LaunchMediaSelect,
+            (_, true) => Key::Unknown(0xE000 | scancode),
+            _ => Key::Unknown(scancode),
+        }
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "{self:?}"),
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = ParseKeyError;
+
+    /// Parses what `Display` writes, so bindings round-trip through config files.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unknown = s
+            .strip_prefix("Unknown(")
+            .and_then(|s| s.strip_suffix(')'))
+            .and_then(|code| code.parse().ok())
+            .map(Key::Unknown);
+
+        Self::from_name(s).or(unknown).ok_or(ParseKeyError)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseKeyError;
+
+impl fmt::Display for ParseKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("unknown key name")
+    }
+}
+
+impl std::error::Error for ParseKeyError {}
+
+/// Modifier keys held, and lock keys toggled on, when an input event happened.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub const SHIFT: Self = Self(1 << 0);
+    pub const CTRL: Self = Self(1 << 1);
+    pub const ALT: Self = Self(1 << 2);
+    pub const LOGO: Self = Self(1 << 3);
+    pub const CAPS_LOCK: Self = Self(1 << 4);
+    pub const NUM_LOCK: Self = Self(1 << 5);
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn set(&mut self, other: Self, value: bool) {
+        match value {
+            true => self.0 |= other.0,
+            false => self.0 &= !other.0,
+        }
+    }
+}
+
+impl ops::BitOr for Modifiers {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl ops::BitOrAssign for Modifiers {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip() {
+        for &(key, name) in NAMES {
+            assert_eq!(key.name(), Some(name));
+            assert_eq!(Key::from_name(name), Some(key));
+            assert_eq!(key.to_string().parse(), Ok(key));
+        }
+    }
+
+    #[test]
+    fn names_ignore_case() {
+        assert_eq!(Key::from_name("pageup"), Some(Key::PageUp));
+        assert_eq!("NUMPADENTER".parse(), Ok(Key::NumpadEnter));
+    }
+
+    #[test]
+    fn unknown_round_trips() {
+        let key = Key::Unknown(0xE07F);
+
+        assert_eq!(key.name(), None);
+        assert_eq!(key.to_string(), "Unknown(57471)");
+        assert_eq!(key.to_string().parse(), Ok(key));
+    }
+
+    #[test]
+    fn unknown_names_are_rejected() {
+        assert_eq!(Key::from_name("Hyper"), None);
+        assert_eq!("Hyper".parse::<Key>(), Err(ParseKeyError));
+        assert_eq!("Unknown(-1)".parse::<Key>(), Err(ParseKeyError));
+        assert_eq!("Unknown(1".parse::<Key>(), Err(ParseKeyError));
+    }
+
+    #[test]
+    fn scancodes_follow_the_windows_convention() {
+        assert_eq!(Key::from_scancode(0x1E, false), Key::A);
+        assert_eq!(Key::from_scancode(0x45, false), Key::Pause);
+        assert_eq!(Key::from_scancode(0x45, true), Key::NumLock);
+        assert_eq!(Key::from_scancode(0x1D, false), Key::LControl);
+        assert_eq!(Key::from_scancode(0x1D, true), Key::RControl);
+        assert_eq!(Key::from_scancode(0x1C, true), Key::NumpadEnter);
+        assert_eq!(Key::from_scancode(0x7F, true), Key::Unknown(0xE07F));
+    }
+}
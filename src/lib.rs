@@ -1,11 +1,480 @@
+use core::{ffi::CStr, fmt};
+use std::time::Instant;
+
+#[cfg(feature = "gl")]
+use core::{cell::Cell, ffi::c_void};
+
+pub mod headless;
+pub mod key;
+
+// The event queue, window count and control flow the windows of a thread share.
+#[cfg(any(windows, unix))]
+mod thread;
+
 #[cfg(windows)]
 pub mod win32;
 
 #[cfg(windows)]
-pub use win32::key;
+pub use win32::Window;
+
+#[cfg(all(windows, feature = "gl"))]
+pub use win32::GlContext;
+
+#[cfg(unix)]
+pub mod x11;
+
+#[cfg(unix)]
+pub use self::x11::Window;
+
+#[cfg(all(unix, feature = "gl"))]
+pub use self::x11::GlContext;
+
+pub use key::{Key, Modifiers};
 
 pub enum Event {
     Idle,
-    Press(usize),
-    Release(usize),
+    /// `key` follows the active layout, `physical` is where the key sits on a US layout. `repeat`
+    /// is set when the key was already down and this press comes from auto-repeat.
+    Press {
+        key: Key,
+        physical: Key,
+        scancode: u32,
+        extended: bool,
+        repeat: bool,
+        modifiers: Modifiers,
+    },
+
+    Release {
+        key: Key,
+        physical: Key,
+        scancode: u32,
+        extended: bool,
+        modifiers: Modifiers,
+    },
+
+    /// Character typed, after keyboard layout, modifiers and dead keys are applied. Control
+    /// characters such as backspace or return are reported too.
+    Text(char),
+
+    /// `position` is in client coordinates.
+    Motion {
+        position: [i32; 2],
+        modifiers: Modifiers,
+    },
+
+    ButtonPress {
+        button: Button,
+        modifiers: Modifiers,
+    },
+
+    ButtonRelease {
+        button: Button,
+        modifiers: Modifiers,
+    },
+
+    /// `delta` is in notches, positive away from the user.
+    Wheel {
+        delta: f32,
+        modifiers: Modifiers,
+    },
+
+    /// New inner size.
+    Resize([u32; 2]),
+    Focus(bool),
+    Minimized,
+    Restored,
+
+    /// The user asked to close the window. Nothing happens unless the application calls `close`.
+    CloseRequested,
+
+    /// The window is gone after a call to `close`, and won't report anything else.
+    Destroyed,
+}
+
+/// Tells apart the windows whose events come through the same loop.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WindowId(pub(crate) usize);
+
+/// What the event loop does once it has handed out every pending event.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ControlFlow {
+    /// Report `Idle` over and over, for applications rendering continuously.
+    #[default]
+    Poll,
+
+    /// Report `Idle` once, then sleep until something else happens.
+    Wait,
+
+    /// Like `Wait`, but wake up and report `Idle` again once the deadline passes.
+    WaitUntil(Instant),
+
+    /// Return from the loop once the callback does, leaving the windows open. The setting goes
+    /// back to `Poll` on the way out, so the loop can be run again.
+    Exit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Button {
+    Left,
+    Right,
+    Middle,
+    X1,
+    X2,
+}
+
+/// A platform call that failed while creating a window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Error {
+    pub function: &'static str,
+
+    /// `GetLastError` on Windows, 0 where the platform doesn't report a code.
+    pub code: u32,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.code {
+            0 => write!(f, "{} failed", self.function),
+            code => write!(f, "{} failed with error {code}", self.function),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Options for creating a window, with chainable setters.
+///
+/// ```no_run
+/// use xenocore::{self as xc, Backend};
+///
+/// let window: xc::Window = xc::WindowBuilder::new(c"splash")
+///     .inner_size([640, 480])
+///     .decorated(false)
+///     .build()?;
+/// # Ok::<_, xc::Error>(())
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct WindowBuilder<'a> {
+    pub(crate) name: &'a CStr,
+    pub(crate) inner_size: Option<[u32; 2]>,
+    pub(crate) position: Option<[i32; 2]>,
+    pub(crate) resizable: bool,
+    pub(crate) decorated: bool,
+    pub(crate) visible: bool,
+    pub(crate) min_size: Option<[u32; 2]>,
+    pub(crate) max_size: Option<[u32; 2]>,
+
+    #[cfg(feature = "gl")]
+    pub(crate) gl_configs: &'a [GlConfig],
+
+    #[cfg(feature = "gl")]
+    pub(crate) gl_attributes: Option<GlAttributes>,
+
+    #[cfg(feature = "gl")]
+    pub(crate) gl_context: bool,
+}
+
+impl<'a> WindowBuilder<'a> {
+    pub fn new(name: &'a CStr) -> Self {
+        Self {
+            name,
+            inner_size: None,
+            position: None,
+            resizable: true,
+            decorated: true,
+            visible: true,
+            min_size: None,
+            max_size: None,
+
+            #[cfg(feature = "gl")]
+            gl_configs: &[GlConfig::DEFAULT],
+
+            #[cfg(feature = "gl")]
+            gl_attributes: None,
+
+            #[cfg(feature = "gl")]
+            gl_context: true,
+        }
+    }
+
+    /// Size of the client area, not counting borders and title bar. Left to the platform if unset.
+    pub fn inner_size(mut self, size: [u32; 2]) -> Self {
+        self.inner_size = Some(size);
+        self
+    }
+
+    /// Top-left corner of the outer frame, in screen coordinates.
+    pub fn position(mut self, position: [i32; 2]) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    pub fn resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    /// Undecorated windows have neither border nor title bar.
+    pub fn decorated(mut self, decorated: bool) -> Self {
+        self.decorated = decorated;
+        self
+    }
+
+    pub fn visible(mut self, visible: bool) -> Self {
+        self.visible = visible;
+        self
+    }
+
+    /// Smallest inner size the user can resize the window to.
+    pub fn min_size(mut self, size: [u32; 2]) -> Self {
+        self.min_size = Some(size);
+        self
+    }
+
+    /// Largest inner size the user can resize the window to.
+    pub fn max_size(mut self, size: [u32; 2]) -> Self {
+        self.max_size = Some(size);
+        self
+    }
+
+    /// Framebuffer formats to try, best first. The first one the driver fully provides is used, or
+    /// failing that whatever comes closest to the first one.
+    #[cfg(feature = "gl")]
+    pub fn gl_configs(mut self, configs: &'a [GlConfig]) -> Self {
+        self.gl_configs = configs;
+        self
+    }
+
+    /// Opts into creating the context through WGL_ARB_create_context or GLX_ARB_create_context.
+    /// Multisampled and sRGB formats depend on it on Windows too.
+    #[cfg(feature = "gl")]
+    pub fn gl_attributes(mut self, attributes: GlAttributes) -> Self {
+        self.gl_attributes = Some(attributes);
+        self
+    }
+
+    /// Whether the window gets a context of its own, current from the start. Windows sharing a
+    /// `GlContext` can do without.
+    #[cfg(feature = "gl")]
+    pub fn gl_context(mut self, enabled: bool) -> Self {
+        self.gl_context = enabled;
+        self
+    }
+
+    pub fn build<B: Backend>(&self) -> Result<B, Error> {
+        B::build(self)
+    }
+}
+
+/// A framebuffer format, in bits per pixel. Color bits don't count alpha.
+#[cfg(feature = "gl")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GlConfig {
+    pub color_bits: u8,
+    pub alpha_bits: u8,
+    pub depth_bits: u8,
+    pub stencil_bits: u8,
+
+    /// Samples per pixel, 0 without multisampling.
+    pub samples: u8,
+    pub double_buffer: bool,
+
+    /// Whether `GL_FRAMEBUFFER_SRGB` can be enabled.
+    pub srgb: bool,
+}
+
+#[cfg(feature = "gl")]
+impl GlConfig {
+    /// 24-bit color and a 32-bit depth buffer, double-buffered.
+    pub const DEFAULT: Self = Self {
+        color_bits: 24,
+        alpha_bits: 0,
+        depth_bits: 32,
+        stencil_bits: 0,
+        samples: 0,
+        double_buffer: true,
+        srgb: false,
+    };
+
+    /// Whether `self`, as obtained, provides at least what `wanted` asks for.
+    pub fn satisfies(&self, wanted: &Self) -> bool {
+        self.color_bits >= wanted.color_bits
+            && self.alpha_bits >= wanted.alpha_bits
+            && self.depth_bits >= wanted.depth_bits
+            && self.stencil_bits >= wanted.stencil_bits
+            && self.samples >= wanted.samples
+            && self.double_buffer == wanted.double_buffer
+            && (self.srgb || !wanted.srgb)
+    }
+}
+
+#[cfg(feature = "gl")]
+impl Default for GlConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// A context of a given version, for drivers that support choosing one. Those that don't get a
+/// legacy context as if nothing was asked for, as do 9x drivers. A driver that supports choosing
+/// but can't provide the version or profile asked for fails `GlContext::new` and `build` instead.
+#[cfg(feature = "gl")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GlAttributes {
+    pub version: [u8; 2],
+    pub profile: GlProfile,
+    pub debug: bool,
+}
+
+/// Only matters from 3.2 on, earlier versions have no profiles.
+#[cfg(feature = "gl")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum GlProfile {
+    #[default]
+    Core,
+    Compatibility,
+}
+
+#[cfg(feature = "gl")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Vsync {
+    Off,
+    On,
+
+    /// Waits for the display unless the frame is already late, in which case it tears instead.
+    Adaptive,
+}
+
+/// Runs with the window around `swap_buffers`, for frame pacing or capturing what's on screen.
+#[cfg(feature = "gl")]
+pub type PresentHook<W> = Box<dyn FnMut(&W)>;
+
+// Each hook is taken out while it runs, so swapping from inside one doesn't recurse.
+#[cfg(feature = "gl")]
+pub(crate) struct PresentHooks<W> {
+    before: HookSlot<W>,
+    after: HookSlot<W>,
+}
+
+#[cfg(feature = "gl")]
+impl<W> PresentHooks<W> {
+    pub(crate) fn new() -> Self {
+        Self {
+            before: HookSlot::new(),
+            after: HookSlot::new(),
+        }
+    }
+
+    pub(crate) fn set_before(&self, hook: Option<PresentHook<W>>) {
+        self.before.set(hook);
+    }
+
+    pub(crate) fn set_after(&self, hook: Option<PresentHook<W>>) {
+        self.after.set(hook);
+    }
+
+    pub(crate) fn present(&self, window: &W, swap: impl FnOnce()) {
+        self.before.run(window);
+        swap();
+        self.after.run(window);
+    }
+}
+
+#[cfg(feature = "gl")]
+struct HookSlot<W> {
+    hook: Cell<Option<PresentHook<W>>>,
+
+    // Bumped by every change, so a hook that replaces itself isn't put back afterwards.
+    generation: Cell<u32>,
+}
+
+#[cfg(feature = "gl")]
+impl<W> HookSlot<W> {
+    fn new() -> Self {
+        Self {
+            hook: Cell::new(None),
+            generation: Cell::new(0),
+        }
+    }
+
+    fn set(&self, hook: Option<PresentHook<W>>) {
+        self.generation.set(self.generation.get().wrapping_add(1));
+        self.hook.set(hook);
+    }
+
+    fn run(&self, window: &W) {
+        let Some(mut hook) = self.hook.take() else {
+            return;
+        };
+
+        let generation = self.generation.get();
+        hook(window);
+
+        if self.generation.get() == generation {
+            self.hook.set(Some(hook));
+        }
+    }
+}
+
+/// Operations every platform window provides, so game code can stay backend-agnostic.
+pub trait Backend: Sized {
+    /// A visible, resizable, decorated window with the platform's default geometry.
+    fn new(name: &CStr) -> Result<Self, Error> {
+        Self::build(&WindowBuilder::new(name))
+    }
+
+    fn build(builder: &WindowBuilder) -> Result<Self, Error>;
+    fn id(&self) -> WindowId;
+
+    /// `[0, 0]` once the window has been closed.
+    fn inner_size(&self) -> [u32; 2];
+
+    /// Dispatches events for every window on the thread, returning once all of them are closed or
+    /// the callback sets `ControlFlow::Exit`. `Idle` is reported with the id of the window the loop
+    /// was started from.
+    fn event_loop(&self, cb: impl FnMut(WindowId, Event));
+
+    /// Hands out the events this window has pending, for applications running a loop of their own.
+    /// Events for other windows on the thread stay queued for them, and `Idle` is never reported.
+    fn poll_events(&self) -> impl Iterator<Item = Event> + '_;
+
+    /// Like `poll_events`, but sleeps until there's at least one event, unless the window is gone.
+    fn wait_events(&self) -> impl Iterator<Item = Event> + '_;
+
+    fn close(&self);
+
+    /// Applies to the loop running on the thread, and may be changed from inside its callback.
+    fn set_control_flow(&self, flow: ControlFlow);
+
+    /// Whether auto-repeated presses are reported, which they are by default.
+    fn set_key_repeat(&self, enabled: bool);
+
+    /// Presents the back buffer, running the present hooks before and after.
+    #[cfg(feature = "gl")]
+    fn swap_buffers(&self);
+
+    /// Replaces the hook run right before presenting, or removes it with `None`.
+    #[cfg(feature = "gl")]
+    fn set_before_present(&self, hook: Option<PresentHook<Self>>);
+
+    /// Replaces the hook run right after presenting, once the swap has been issued.
+    #[cfg(feature = "gl")]
+    fn set_after_present(&self, hook: Option<PresentHook<Self>>);
+
+    /// Asks the driver for a swap interval, returning whether it took it. `Adaptive` needs the
+    /// swap_control_tear extension. On Windows it goes through the context current on the thread.
+    #[cfg(feature = "gl")]
+    fn set_vsync(&self, vsync: Vsync) -> bool;
+
+    /// `None` if the driver doesn't say.
+    #[cfg(feature = "gl")]
+    fn vsync(&self) -> Option<Vsync>;
+
+    /// The framebuffer format the driver handed out, which may fall short of every one asked for.
+    #[cfg(feature = "gl")]
+    fn pixel_format(&self) -> GlConfig;
+
+    /// Looks up a GL entry point, returning null if the driver doesn't provide it.
+    #[cfg(feature = "gl")]
+    fn proc_address(&self, name: &CStr) -> *const c_void;
 }
@@ -0,0 +1,126 @@
+use core::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+
+use crate::{ControlFlow, Event, WindowId};
+
+thread_local! {
+    // Events reported by the platform for every window on the thread, waiting for the event loop
+    // or `poll_events` to hand them out. Platforms call back from inside all sorts of calls, so the
+    // application's callback is never run from there to keep it from re-entering.
+    static QUEUE: RefCell<VecDeque<(WindowId, Event)>> = const { RefCell::new(VecDeque::new()) };
+
+    // Windows created on this thread and not destroyed yet, the event loop runs until there are
+    // none left.
+    static WINDOWS: Cell<usize> = const { Cell::new(0) };
+
+    static CONTROL_FLOW: Cell<ControlFlow> = const { Cell::new(ControlFlow::Poll) };
+}
+
+// What handling one message from the platform came to.
+pub(crate) enum Pumped {
+    Nothing,
+    Handled,
+
+    // WM_QUIT, which something else on the thread may post.
+    #[cfg(windows)]
+    Quit,
+}
+
+pub(crate) fn emit(id: WindowId, event: Event) {
+    QUEUE.with_borrow_mut(|queue| queue.push_back((id, event)));
+}
+
+// Takes the oldest event for one window, leaving those for the others in place.
+pub(crate) fn take(id: WindowId) -> Option<Event> {
+    QUEUE.with_borrow_mut(|queue| {
+        let index = queue.iter().position(|&(other, _)| other == id)?;
+        queue.remove(index).map(|(_, event)| event)
+    })
+}
+
+pub(crate) fn queued(id: WindowId) -> bool {
+    QUEUE.with_borrow(|queue| queue.iter().any(|&(other, _)| other == id))
+}
+
+// Drops whatever a window being dropped left pending.
+pub(crate) fn forget(id: WindowId) {
+    QUEUE.with_borrow_mut(|queue| queue.retain(|&(other, _)| other != id));
+}
+
+pub(crate) fn opened() {
+    WINDOWS.set(WINDOWS.get() + 1);
+}
+
+pub(crate) fn destroyed() {
+    WINDOWS.set(WINDOWS.get() - 1);
+}
+
+pub(crate) fn set_control_flow(flow: ControlFlow) {
+    CONTROL_FLOW.set(flow);
+}
+
+// Hands out queued events, then `Idle` for `id` once `pump` finds nothing more, until no windows
+// are left or the callback asks to exit. In between batches, `wait` sleeps as the control flow
+// says.
+pub(crate) fn run(
+    id: WindowId,
+    mut cb: impl FnMut(WindowId, Event),
+    mut pump: impl FnMut() -> Pumped,
+    wait: impl Fn(ControlFlow),
+) {
+    // Set once `Idle` was reported for the events handled so far, so it isn't reported again until
+    // something else happens or the deadline passes.
+    let mut idle = false;
+
+    loop {
+        // The borrow ends before calling back, which may well queue more events or even run a
+        // nested loop.
+        while let Some((id, event)) = QUEUE.with_borrow_mut(VecDeque::pop_front) {
+            cb(id, event);
+            idle = false;
+
+            // Whatever is left stays queued for the next loop.
+            if exiting() {
+                return;
+            }
+        }
+
+        if WINDOWS.get() == 0 || exiting() {
+            break;
+        }
+
+        if idle {
+            wait(CONTROL_FLOW.get());
+        }
+
+        match pump() {
+            // Messages handled inside the pump may have reported something, which comes first.
+            Pumped::Nothing => {
+                if QUEUE.with_borrow(VecDeque::is_empty) {
+                    cb(id, Event::Idle);
+                    idle = true;
+                }
+            }
+
+            Pumped::Handled => idle = false,
+
+            #[cfg(windows)]
+            Pumped::Quit => break,
+        }
+    }
+}
+
+// Pumps until something is queued for `id`, giving up once `gone` says nothing ever will be.
+pub(crate) fn wait_for(id: WindowId, gone: impl Fn() -> bool, mut pump: impl FnMut()) {
+    while !gone() && !queued(id) {
+        pump();
+    }
+}
+
+// Whether the callback asked the loop to return, resetting the request if so.
+fn exiting() -> bool {
+    CONTROL_FLOW.get() == ControlFlow::Exit && {
+        CONTROL_FLOW.set(ControlFlow::Poll);
+        true
+    }
+}
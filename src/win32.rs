@@ -1,181 +1,1565 @@
-use core::{ffi::CStr, mem, ptr};
+use core::{
+    cell::Cell,
+    iter,
+    marker::PhantomData,
+    mem, ptr,
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+};
+use std::{
+    panic,
+    sync::{Mutex, Once, OnceLock, PoisonError},
+    time::Instant,
+};
+
+use core::ffi::CStr;
+
+#[cfg(feature = "gl")]
+use core::ffi::c_void;
+
+use windows_sys::Win32::{
+    Foundation::{GetLastError, BOOL, HWND, LPARAM, LRESULT, POINT, RECT, WPARAM},
+    Globalization::{MultiByteToWideChar, WideCharToMultiByte, CP_ACP},
+    Graphics::Gdi::{
+        ChangeDisplaySettingsA, EnumDisplaySettingsA, GetDC, ReleaseDC, CDS_FULLSCREEN, DEVMODEA,
+        DISP_CHANGE_SUCCESSFUL, DM_BITSPERPEL, DM_DISPLAYFREQUENCY, DM_PELSHEIGHT, DM_PELSWIDTH,
+        HDC, HMONITOR, MONITORENUMPROC, MONITORINFO, MONITOR_DEFAULTTONEAREST,
+        MONITOR_DEFAULTTOPRIMARY,
+    },
+    System::LibraryLoader::{GetModuleHandleA, GetProcAddress},
+    UI::Input::KeyboardAndMouse as km,
+    UI::WindowsAndMessaging::{
+        AdjustWindowRect, CreateWindowExA, DefWindowProcW, DestroyWindow, DispatchMessageW,
+        GetClientRect, GetSystemMetrics, GetWindowLongA, GetWindowLongPtrA, GetWindowPlacement,
+        GetWindowRect, GetWindowTextA, GetWindowTextLengthA, IsIconic, IsWindowVisible, IsZoomed,
+        LoadCursorA, MsgWaitForMultipleObjects, PeekMessageW, RegisterClassW,
+        RegisterWindowMessageA, SetForegroundWindow, SetWindowLongA, SetWindowLongPtrA,
+        SetWindowPlacement, SetWindowPos, SetWindowTextA, ShowWindow, TranslateMessage,
+        WaitMessage, CREATESTRUCTW, CS_HREDRAW, CS_VREDRAW, CW_USEDEFAULT, GWLP_USERDATA,
+        GWL_STYLE, HWND_NOTOPMOST, HWND_TOP, HWND_TOPMOST, IDC_ARROW, MINMAXINFO, MSG, PM_REMOVE,
+        QS_ALLINPUT, SIZE_MINIMIZED, SM_CXSCREEN, SM_CYSCREEN, SWP_FRAMECHANGED, SWP_NOACTIVATE,
+        SWP_NOMOVE, SWP_NOSIZE, SWP_NOZORDER, SWP_SHOWWINDOW, SW_HIDE, SW_MAXIMIZE, SW_MINIMIZE,
+        SW_RESTORE, SW_SHOW, WHEEL_DELTA, WINDOWPLACEMENT, WM_ACTIVATEAPP, WM_CHAR, WM_CLOSE,
+        WM_DESTROY, WM_GETMINMAXINFO, WM_KEYDOWN, WM_KEYUP, WM_KILLFOCUS, WM_LBUTTONDOWN,
+        WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_NCCREATE,
+        WM_QUIT, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SETFOCUS, WM_SIZE, WM_SYSKEYDOWN, WM_SYSKEYUP,
+        WM_XBUTTONDOWN, WM_XBUTTONUP, WNDCLASSW, WS_MAXIMIZEBOX, WS_OVERLAPPEDWINDOW, WS_POPUP,
+        WS_THICKFRAME, WS_VISIBLE, XBUTTON1,
+    },
+};
+
+use crate::{
+    thread::{self, Pumped},
+    Backend, Button, ControlFlow, Error, Event, Key, Modifiers, WindowBuilder, WindowId,
+};
+
+#[cfg(feature = "gl")]
+use windows_sys::Win32::Graphics::OpenGL::{
+    wglCreateContext, wglDeleteContext, wglGetCurrentContext, wglGetProcAddress, wglMakeCurrent,
+    wglShareLists, ChoosePixelFormat, DescribePixelFormat, SetPixelFormat, SwapBuffers, HGLRC,
+    PFD_DOUBLEBUFFER, PFD_DRAW_TO_WINDOW, PFD_GENERIC_ACCELERATED, PFD_GENERIC_FORMAT,
+    PFD_SUPPORT_OPENGL, PFD_TYPE_RGBA, PIXELFORMATDESCRIPTOR,
+};
+
+#[cfg(feature = "gl")]
+use crate::{GlAttributes, GlConfig, GlProfile, PresentHook, PresentHooks, Vsync};
+
+// Windows 95 without IntelliMouse support has no WM_MOUSEWHEEL, the mouse driver broadcasts this
+// registered message instead.
+static MSH_MOUSEWHEEL: AtomicU32 = AtomicU32::new(0);
+
+// Window class shared by every window, registered along with the first one. Locked so windows
+// created on different threads don't race to register it.
+static CLASS: Mutex<u16> = Mutex::new(0);
+
+// Set while the display is in a mode we switched to.
+static MODE_CHANGED: AtomicBool = AtomicBool::new(false);
+static PANIC_HOOK: Once = Once::new();
+
+/// Once closed, the setters do nothing: Windows may have handed the handle to another window.
+pub struct Window {
+    pub hwnd: HWND,
+    pub hdc: HDC,
+
+    /// The window's own context, unless it was built without one.
+    #[cfg(feature = "gl")]
+    pub context: Option<GlContext>,
+
+    state: Box<State>,
+
+    #[cfg(feature = "gl")]
+    pixel_format: GlConfig,
+
+    // Kept for making more contexts like the first one.
+    #[cfg(feature = "gl")]
+    arb: Arb,
+
+    #[cfg(feature = "gl")]
+    gl_attributes: Option<GlAttributes>,
+
+    #[cfg(feature = "gl")]
+    present_hooks: PresentHooks<Self>,
+
+    // Windows belong to the thread that created them, as do its queue and window count.
+    _thread: PhantomData<*const ()>,
+}
+
+/// A resolution, color depth and refresh rate the display can be switched to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DisplayMode {
+    pub size: [u32; 2],
+    pub bits_per_pixel: u32,
+
+    /// In hertz, with 0 and 1 standing for the hardware default.
+    pub refresh_rate: u32,
+}
+
+// Reachable from `message_callback` through `GWLP_USERDATA`.
+struct State {
+    hwnd: Cell<HWND>,
+    destroyed: Cell<bool>,
+    minimized: Cell<bool>,
+    high_surrogate: Cell<Option<u16>>,
+    key_repeat: Cell<bool>,
+
+    // Outer size limits, as WM_GETMINMAXINFO wants them.
+    min_size: Option<[i32; 2]>,
+    max_size: Option<[i32; 2]>,
+
+    // Mode in use while in exclusive fullscreen, and the style and placement to go back to.
+    exclusive: Cell<Option<DisplayMode>>,
+    windowed: Cell<Option<(u32, WINDOWPLACEMENT)>>,
+}
+
+impl State {
+    fn emit(&self, event: Event) {
+        thread::emit(WindowId(self.hwnd.get() as _), event);
+    }
+}
+
+impl Window {
+    /// Modes the primary display supports, in the order the driver lists them.
+    pub fn display_modes() -> Vec<DisplayMode> {
+        (0..)
+            .map_while(|idx| {
+                let mut devmode = DEVMODEA {
+                    dmSize: mem::size_of::<DEVMODEA>() as _,
+                    ..unsafe { mem::zeroed() }
+                };
+
+                let success = unsafe { EnumDisplaySettingsA(ptr::null(), idx, &mut devmode) };
+
+                (success != 0).then_some(DisplayMode {
+                    size: [devmode.dmPelsWidth, devmode.dmPelsHeight],
+                    bits_per_pixel: devmode.dmBitsPerPel,
+                    refresh_rate: devmode.dmDisplayFrequency,
+                })
+            })
+            .collect()
+    }
+
+    /// Switches the display to `mode` and covers it with the window, or goes back to the desktop
+    /// mode and the previous frame with `None`. Returns whether the display accepted the mode.
+    ///
+    /// The desktop mode is also restored while the window is inactive, when it's dropped and when
+    /// the program panics.
+    pub fn set_exclusive_fullscreen(&self, mode: Option<DisplayMode>) -> bool {
+        if self.state.destroyed.get() {
+            return false;
+        }
+
+        let Some(mode) = mode else {
+            self.leave_fullscreen();
+            return true;
+        };
+
+        if !change_display_mode(Some(mode)) {
+            return false;
+        }
+
+        self.state.exclusive.set(Some(mode));
+        self.cover([0; 2], mode.size, HWND_TOPMOST);
+
+        true
+    }
+
+    /// Turns the window into a borderless one covering `monitor`, or restores its previous frame
+    /// with `None`. The GL context survives either way.
+    pub fn set_fullscreen(&self, monitor: Option<Monitor>) {
+        if self.state.destroyed.get() {
+            return;
+        }
+
+        let Some(monitor) = monitor else {
+            self.leave_fullscreen();
+            return;
+        };
+
+        // Coming from exclusive fullscreen, the window is still topmost.
+        let after = match self.state.exclusive.take() {
+            Some(_) => {
+                change_display_mode(None);
+                HWND_NOTOPMOST
+            }
+            None => HWND_TOP,
+        };
+
+        self.cover(monitor.position(), monitor.size(), after);
+    }
+
+    /// The monitor the window is mostly on.
+    pub fn current_monitor(&self) -> Monitor {
+        let handle = Multimon::get().map_or(0, |multimon| unsafe {
+            (multimon.monitor_from_window)(self.hwnd, MONITOR_DEFAULTTONEAREST)
+        });
+
+        Monitor { handle }
+    }
+
+    pub fn title(&self) -> String {
+        let len = unsafe { GetWindowTextLengthA(self.hwnd) };
+        let mut bytes = vec![0; len as usize + 1];
+
+        let len = unsafe { GetWindowTextA(self.hwnd, bytes.as_mut_ptr(), bytes.len() as _) };
+        from_ansi(&bytes[..len as usize])
+    }
+
+    /// Characters missing from the active code page come out as `?`.
+    pub fn set_title(&self, title: &str) {
+        if self.state.destroyed.get() {
+            return;
+        }
+
+        let mut bytes = to_ansi(title);
+        bytes.push(0);
+
+        unsafe { SetWindowTextA(self.hwnd, bytes.as_ptr()) };
+    }
+
+    /// Resizes the client area, keeping the frame around it.
+    pub fn set_inner_size(&self, size: [u32; 2]) {
+        if self.state.destroyed.get() {
+            return;
+        }
+
+        let style = unsafe { GetWindowLongA(self.hwnd, GWL_STYLE) } as u32;
+        let [width, height] = outer_size(size, style);
+
+        let flags = SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE;
+        unsafe { SetWindowPos(self.hwnd, 0, 0, 0, width, height, flags) };
+    }
+
+    /// Size including the frame.
+    pub fn outer_size(&self) -> [u32; 2] {
+        let rect = self.rect();
+        [(rect.right - rect.left) as _, (rect.bottom - rect.top) as _]
+    }
+
+    pub fn set_outer_size(&self, size: [u32; 2]) {
+        if self.state.destroyed.get() {
+            return;
+        }
+
+        let [width, height] = size;
+
+        let flags = SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE;
+        unsafe { SetWindowPos(self.hwnd, 0, 0, 0, width as _, height as _, flags) };
+    }
+
+    /// Top-left corner of the frame, in screen coordinates.
+    pub fn position(&self) -> [i32; 2] {
+        let rect = self.rect();
+        [rect.left, rect.top]
+    }
+
+    pub fn set_position(&self, position: [i32; 2]) {
+        if self.state.destroyed.get() {
+            return;
+        }
+
+        let [x, y] = position;
+
+        let flags = SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE;
+        unsafe { SetWindowPos(self.hwnd, 0, x, y, 0, 0, flags) };
+    }
+
+    pub fn is_visible(&self) -> bool {
+        unsafe { IsWindowVisible(self.hwnd) != 0 }
+    }
+
+    pub fn set_visible(&self, visible: bool) {
+        if self.state.destroyed.get() {
+            return;
+        }
+
+        let command = match visible {
+            true => SW_SHOW,
+            false => SW_HIDE,
+        };
+
+        unsafe { ShowWindow(self.hwnd, command) };
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        unsafe { IsIconic(self.hwnd) != 0 }
+    }
+
+    pub fn is_maximized(&self) -> bool {
+        unsafe { IsZoomed(self.hwnd) != 0 }
+    }
+
+    pub fn minimize(&self) {
+        if self.state.destroyed.get() {
+            return;
+        }
+
+        unsafe { ShowWindow(self.hwnd, SW_MINIMIZE) };
+    }
+
+    pub fn maximize(&self) {
+        if self.state.destroyed.get() {
+            return;
+        }
+
+        unsafe { ShowWindow(self.hwnd, SW_MAXIMIZE) };
+    }
+
+    /// Undoes `minimize` and `maximize`.
+    pub fn restore(&self) {
+        if self.state.destroyed.get() {
+            return;
+        }
+
+        unsafe { ShowWindow(self.hwnd, SW_RESTORE) };
+    }
+
+    /// Brings the window to the front and gives it keyboard focus. Windows may only flash it in
+    /// the taskbar instead if another application is in the foreground.
+    pub fn focus(&self) {
+        if self.state.destroyed.get() {
+            return;
+        }
+
+        unsafe { SetForegroundWindow(self.hwnd) };
+    }
+
+    fn rect(&self) -> RECT {
+        let mut rect = unsafe { mem::zeroed() };
+
+        let success = unsafe { GetWindowRect(self.hwnd, &mut rect) };
+        assert!(success != 0);
+
+        rect
+    }
+
+    // Restyles the window into a pop-up over the given area, saving the frame to go back to.
+    fn cover(&self, position: [i32; 2], size: [u32; 2], after: HWND) {
+        if self.state.windowed.get().is_none() {
+            let style = unsafe { GetWindowLongA(self.hwnd, GWL_STYLE) } as u32;
+
+            let mut placement = WINDOWPLACEMENT {
+                length: mem::size_of::<WINDOWPLACEMENT>() as _,
+                ..unsafe { mem::zeroed() }
+            };
+
+            unsafe { GetWindowPlacement(self.hwnd, &mut placement) };
+            self.state.windowed.set(Some((style, placement)));
+        }
+
+        let [x, y] = position;
+        let [width, height] = size;
+
+        unsafe { SetWindowLongA(self.hwnd, GWL_STYLE, (WS_POPUP | WS_VISIBLE) as _) };
+
+        unsafe {
+            SetWindowPos(
+                self.hwnd,
+                after,
+                x,
+                y,
+                width as _,
+                height as _,
+                SWP_FRAMECHANGED | SWP_SHOWWINDOW,
+            )
+        };
+    }
+
+    fn leave_fullscreen(&self) {
+        if self.state.exclusive.take().is_some() {
+            change_display_mode(None);
+        }
+
+        let Some((style, placement)) = self.state.windowed.take() else {
+            return;
+        };
+
+        unsafe { SetWindowLongA(self.hwnd, GWL_STYLE, style as _) };
+        unsafe { SetWindowPlacement(self.hwnd, &placement) };
+
+        // Placement doesn't cover z-order, nor does it notice the frame changed.
+        let flags = SWP_NOMOVE | SWP_NOSIZE | SWP_FRAMECHANGED;
+        unsafe { SetWindowPos(self.hwnd, HWND_NOTOPMOST, 0, 0, 0, 0, flags) };
+    }
+
+    // Sets up what `Drop` cleans up, so a failure can bail out halfway.
+    #[cfg_attr(not(feature = "gl"), allow(unused_variables))]
+    fn init(&mut self, builder: &WindowBuilder) -> Result<(), Error> {
+        self.hdc = unsafe { GetDC(self.hwnd) };
+        check(self.hdc != 0, "GetDC")?;
+
+        #[cfg(feature = "gl")]
+        {
+            // Only bootstrapped when asked for, it takes a throwaway window and context.
+            if builder.gl_attributes.is_some() {
+                self.arb = Arb::load();
+            }
+
+            let (idx, desc) = match self.arb.choose_pixel_format(self.hdc, builder.gl_configs) {
+                Some(found) => found,
+                None => choose_pixel_format(self.hdc, builder.gl_configs)?,
+            };
+
+            let success = unsafe { SetPixelFormat(self.hdc, idx, &desc) };
+            check(success != 0, "SetPixelFormat")?;
+
+            self.pixel_format = self
+                .arb
+                .describe_pixel_format(self.hdc, idx)
+                .unwrap_or_else(|| gl_config(&desc));
+
+            if builder.gl_context {
+                let context = GlContext::new(self, None)?;
+                check(context.make_current(self), "wglMakeCurrent")?;
+                self.context = Some(context);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A display attached to the desktop.
+///
+/// Windows 95 and NT 4 only know about the primary one, which `handle` is 0 for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Monitor {
+    pub handle: HMONITOR,
+}
+
+impl Monitor {
+    pub fn primary() -> Self {
+        let handle = Multimon::get().map_or(0, |multimon| unsafe {
+            (multimon.monitor_from_point)(POINT { x: 0, y: 0 }, MONITOR_DEFAULTTOPRIMARY)
+        });
+
+        Self { handle }
+    }
+
+    pub fn all() -> Vec<Self> {
+        extern "system" fn push(handle: HMONITOR, _: HDC, _: *mut RECT, l: LPARAM) -> BOOL {
+            let monitors = unsafe { &mut *(l as *mut Vec<Monitor>) };
+
+            monitors.push(Monitor { handle });
+            1
+        }
+
+        let Some(multimon) = Multimon::get() else {
+            return vec![Self::primary()];
+        };
+
+        let mut monitors = Vec::new();
+        let data = &mut monitors as *mut Vec<Self> as _;
+
+        unsafe { (multimon.enum_display_monitors)(0, ptr::null(), Some(push), data) };
+        monitors
+    }
+
+    /// Top-left corner in virtual screen coordinates.
+    pub fn position(self) -> [i32; 2] {
+        let rect = self.rect();
+        [rect.left, rect.top]
+    }
+
+    pub fn size(self) -> [u32; 2] {
+        let rect = self.rect();
+        [(rect.right - rect.left) as _, (rect.bottom - rect.top) as _]
+    }
+
+    fn rect(self) -> RECT {
+        let Some(multimon) = Multimon::get() else {
+            return RECT {
+                left: 0,
+                top: 0,
+                right: unsafe { GetSystemMetrics(SM_CXSCREEN) },
+                bottom: unsafe { GetSystemMetrics(SM_CYSCREEN) },
+            };
+        };
+
+        let mut info = MONITORINFO {
+            cbSize: mem::size_of::<MONITORINFO>() as _,
+            ..unsafe { mem::zeroed() }
+        };
+
+        let success = unsafe { (multimon.get_monitor_info)(self.handle, &mut info) };
+        assert!(success != 0);
+
+        info.rcMonitor
+    }
+}
+
+// The multiple monitor API arrived with Windows 98 and 2000, so it's looked up at runtime the way
+// multimon.h does rather than imported, which would keep binaries from loading on 95 and NT 4.
+struct Multimon {
+    monitor_from_window: unsafe extern "system" fn(HWND, u32) -> HMONITOR,
+    monitor_from_point: unsafe extern "system" fn(POINT, u32) -> HMONITOR,
+    get_monitor_info: unsafe extern "system" fn(HMONITOR, *mut MONITORINFO) -> BOOL,
+    enum_display_monitors:
+        unsafe extern "system" fn(HDC, *const RECT, MONITORENUMPROC, LPARAM) -> BOOL,
+}
+
+impl Multimon {
+    fn get() -> Option<&'static Self> {
+        static MULTIMON: OnceLock<Option<Multimon>> = OnceLock::new();
+        MULTIMON.get_or_init(Self::load).as_ref()
+    }
+
+    fn load() -> Option<Self> {
+        // Only ever asked for the function pointer types below.
+        fn get<T>(name: &CStr) -> Option<T> {
+            let user32 = unsafe { GetModuleHandleA(c"user32.dll".as_ptr() as _) };
+            let proc = unsafe { GetProcAddress(user32, name.as_ptr() as _) }?;
+
+            Some(unsafe { mem::transmute_copy(&proc) })
+        }
+
+        Some(Self {
+            monitor_from_window: get(c"MonitorFromWindow")?,
+            monitor_from_point: get(c"MonitorFromPoint")?,
+            get_monitor_info: get(c"GetMonitorInfoA")?,
+            enum_display_monitors: get(c"EnumDisplayMonitors")?,
+        })
+    }
+}
+
+/// A GL context for windows of the same pixel format, living apart from any of them.
+#[cfg(feature = "gl")]
+pub struct GlContext {
+    pub hglrc: HGLRC,
+}
+
+#[cfg(feature = "gl")]
+impl GlContext {
+    /// Made the way the window's own context is, sharing textures, buffers and display lists with
+    /// `share` if given.
+    pub fn new(window: &Window, share: Option<&GlContext>) -> Result<Self, Error> {
+        let share = share.map_or(0, |share| share.hglrc);
+
+        let modern = window
+            .gl_attributes
+            .and_then(|attributes| window.arb.create_context(window.hdc, attributes, share));
+
+        let context = Self {
+            hglrc: modern.unwrap_or_else(|| unsafe { wglCreateContext(window.hdc) }),
+        };
+
+        match modern {
+            Some(_) => check(context.hglrc != 0, "wglCreateContextAttribsARB")?,
+            None => check(context.hglrc != 0, "wglCreateContext")?,
+        }
+
+        // Legacy contexts are shared after the fact, which only works while they're still empty.
+        if modern.is_none() && share != 0 {
+            let success = unsafe { wglShareLists(share, context.hglrc) };
+            check(success != 0, "wglShareLists")?;
+        }
+
+        Ok(context)
+    }
+
+    /// Any window with the pixel format the context was made for will do.
+    pub fn make_current(&self, window: &Window) -> bool {
+        unsafe { wglMakeCurrent(window.hdc, self.hglrc) != 0 }
+    }
+
+    pub fn make_not_current(&self) {
+        if unsafe { wglGetCurrentContext() } == self.hglrc {
+            unsafe { wglMakeCurrent(0, 0) };
+        }
+    }
+}
+
+#[cfg(feature = "gl")]
+impl Drop for GlContext {
+    fn drop(&mut self) {
+        self.make_not_current();
+        unsafe { wglDeleteContext(self.hglrc) };
+    }
+}
+
+impl Backend for Window {
+    fn build(builder: &WindowBuilder) -> Result<Self, Error> {
+        let instance = unsafe { GetModuleHandleA(ptr::null()) };
+        check(instance != 0, "GetModuleHandleA")?;
+
+        let atom = {
+            let mut atom = CLASS.lock().unwrap_or_else(PoisonError::into_inner);
+
+            if *atom == 0 {
+                // Registered wide so WM_CHAR comes as UTF-16, which unicows provides on 9x.
+                let class = WNDCLASSW {
+                    style: CS_HREDRAW | CS_VREDRAW,
+                    lpfnWndProc: Some(message_callback),
+                    hInstance: instance,
+                    hCursor: unsafe { LoadCursorA(0, IDC_ARROW as _) },
+                    lpszClassName: windows_sys::w!("window"),
+                    ..unsafe { mem::zeroed() }
+                };
+
+                *atom = unsafe { RegisterClassW(&class) };
+                check(*atom != 0, "RegisterClassW")?;
+
+                let wheel = unsafe { RegisterWindowMessageA(c"MSWHEEL_ROLLMSG".as_ptr() as _) };
+                MSH_MOUSEWHEEL.store(wheel, Ordering::Relaxed);
+            }
+
+            *atom
+        };
+
+        let mut style = match builder.decorated {
+            true => WS_OVERLAPPEDWINDOW,
+            false => WS_POPUP,
+        };
+
+        if !builder.resizable {
+            style &= !(WS_THICKFRAME | WS_MAXIMIZEBOX);
+        }
+
+        if builder.visible {
+            style |= WS_VISIBLE;
+        }
+
+        let min_size = builder.min_size.map(|size| outer_size(size, style));
+        let max_size = builder.max_size.map(|size| outer_size(size, style));
+
+        // The first WM_GETMINMAXINFO comes before the state is reachable, so clamp by hand.
+        let inner_size = builder.inner_size.map(|size| {
+            let min = builder.min_size.unwrap_or([0; 2]);
+            let max = builder.max_size.unwrap_or([u32::MAX; 2]);
+
+            [0, 1].map(|i| size[i].max(min[i]).min(max[i]))
+        });
+
+        // Pop-ups don't get a default size, they'd end up 0x0.
+        let [width, height] = match (inner_size, builder.decorated) {
+            (Some(size), _) => outer_size(size, style),
+            (None, true) => [CW_USEDEFAULT; 2],
+            (None, false) => outer_size([640, 480], style),
+        };
+
+        let [x, y] = builder.position.unwrap_or([CW_USEDEFAULT; 2]);
+
+        let state = Box::new(State {
+            hwnd: Cell::new(0),
+            destroyed: Cell::new(false),
+            minimized: Cell::new(false),
+            high_surrogate: Cell::new(None),
+            key_repeat: Cell::new(true),
+            min_size,
+            max_size,
+            exclusive: Cell::new(None),
+            windowed: Cell::new(None),
+        });
+
+        // Picked up on WM_NCCREATE, so messages sent during creation already reach the state.
+        let hwnd = unsafe {
+            CreateWindowExA(
+                0,
+                // Atoms stand in for class names.
+                atom as usize as _,
+                builder.name.as_ptr() as _,
+                style,
+                x,
+                y,
+                width,
+                height,
+                0,
+                0,
+                instance,
+                &*state as *const State as _,
+            )
+        };
+
+        check(hwnd != 0, "CreateWindowExA")?;
+
+        let mut window = Self {
+            hwnd,
+            hdc: 0,
+
+            #[cfg(feature = "gl")]
+            context: None,
+
+            state,
+
+            #[cfg(feature = "gl")]
+            pixel_format: GlConfig::DEFAULT,
+
+            #[cfg(feature = "gl")]
+            arb: Arb::default(),
+
+            #[cfg(feature = "gl")]
+            gl_attributes: builder.gl_attributes,
+
+            #[cfg(feature = "gl")]
+            present_hooks: PresentHooks::new(),
+
+            _thread: PhantomData,
+        };
+
+        window.init(builder)?;
+        Ok(window)
+    }
+
+    fn id(&self) -> WindowId {
+        WindowId(self.hwnd as _)
+    }
+
+    fn inner_size(&self) -> [u32; 2] {
+        if self.state.destroyed.get() {
+            return [0, 0];
+        }
+
+        let mut rect = unsafe { mem::zeroed() };
+
+        let success = unsafe { GetClientRect(self.hwnd, &mut rect) };
+        assert!(success != 0);
+
+        let width = rect.right - rect.left;
+        let height = rect.bottom - rect.top;
+
+        [width as _, height as _]
+    }
+
+    fn event_loop(&self, cb: impl FnMut(WindowId, Event)) {
+        let mut message = unsafe { mem::zeroed::<MSG>() };
+
+        // Messages sent from elsewhere are handled inside `PeekMessageW`.
+        let pump = || {
+            if unsafe { PeekMessageW(&mut message, 0, 0, 0, PM_REMOVE) } == 0 {
+                return Pumped::Nothing;
+            }
+
+            // Still honoured in case something else on the thread posts it.
+            if message.message == WM_QUIT {
+                return Pumped::Quit;
+            }
+
+            // Generates the WM_CHAR messages text input comes from.
+            unsafe { TranslateMessage(&message) };
+            unsafe { DispatchMessageW(&message) };
+            Pumped::Handled
+        };
+
+        thread::run(self.id(), cb, pump, wait);
+    }
+
+    fn poll_events(&self) -> impl Iterator<Item = Event> + '_ {
+        pump();
+        iter::from_fn(|| thread::take(self.id()))
+    }
+
+    fn wait_events(&self) -> impl Iterator<Item = Event> + '_ {
+        pump();
+
+        thread::wait_for(
+            self.id(),
+            || self.state.destroyed.get(),
+            || {
+                wait(ControlFlow::Wait);
+                pump();
+            },
+        );
+
+        self.poll_events()
+    }
+
+    // The handle may already belong to another window once this one is gone.
+    fn close(&self) {
+        if self.state.destroyed.get() {
+            return;
+        }
+
+        unsafe { DestroyWindow(self.hwnd) };
+    }
+
+    fn set_control_flow(&self, flow: ControlFlow) {
+        thread::set_control_flow(flow);
+    }
+
+    fn set_key_repeat(&self, enabled: bool) {
+        self.state.key_repeat.set(enabled);
+    }
+
+    #[cfg(feature = "gl")]
+    fn swap_buffers(&self) {
+        self.present_hooks.present(self, || {
+            unsafe { SwapBuffers(self.hdc) };
+        });
+    }
+
+    #[cfg(feature = "gl")]
+    fn set_before_present(&self, hook: Option<PresentHook<Self>>) {
+        self.present_hooks.set_before(hook);
+    }
 
-use windows_sys::Win32::{
-    Foundation::{HWND, LPARAM, LRESULT, WPARAM},
-    Graphics::Gdi::{GetDC, ReleaseDC, HDC},
-    System::LibraryLoader::GetModuleHandleA,
-    UI::WindowsAndMessaging::{
-        CreateWindowExA, DefWindowProcA, DestroyWindow, DispatchMessageA, GetClientRect,
-        GetWindowLongPtrA, LoadCursorA, PeekMessageA, PostQuitMessage, RegisterClassA,
-        SetWindowLongPtrA, CS_HREDRAW, CS_VREDRAW, CW_USEDEFAULT, GWLP_USERDATA, IDC_ARROW, MSG,
-        PM_REMOVE, WM_CLOSE, WM_DESTROY, WM_KEYDOWN, WM_KEYUP, WM_QUIT, WNDCLASSA,
-        WS_OVERLAPPEDWINDOW, WS_VISIBLE,
-    },
-};
+    #[cfg(feature = "gl")]
+    fn set_after_present(&self, hook: Option<PresentHook<Self>>) {
+        self.present_hooks.set_after(hook);
+    }
 
-use crate::Event;
+    // A negative interval is only accepted with WGL_EXT_swap_control_tear.
+    #[cfg(feature = "gl")]
+    fn set_vsync(&self, vsync: Vsync) -> bool {
+        let interval = match vsync {
+            Vsync::Off => 0,
+            Vsync::On => 1,
+            Vsync::Adaptive => -1,
+        };
 
-#[cfg(feature = "gl")]
-use windows_sys::Win32::Graphics::OpenGL::{
-    wglCreateContext, wglDeleteContext, wglMakeCurrent, ChoosePixelFormat, SetPixelFormat, HGLRC,
-    PFD_DOUBLEBUFFER, PFD_DRAW_TO_WINDOW, PFD_SUPPORT_OPENGL, PFD_TYPE_RGBA, PIXELFORMATDESCRIPTOR,
-};
+        let set = unsafe { crate::wgl_load!(c"wglSwapIntervalEXT", fn(i32) -> BOOL) };
 
-pub struct Window {
-    pub hwnd: HWND,
-    pub hdc: HDC,
+        // Drivers without WGL_EXT_swap_control_tear take -1 as 1, so only what reads back counts.
+        set.is_some_and(|set| unsafe { set(interval) } != 0) && self.vsync() == Some(vsync)
+    }
 
     #[cfg(feature = "gl")]
-    pub hglrc: HGLRC,
+    fn vsync(&self) -> Option<Vsync> {
+        let get = unsafe { crate::wgl_load!(c"wglGetSwapIntervalEXT", fn() -> i32) }?;
+
+        Some(match unsafe { get() } {
+            0 => Vsync::Off,
+            ..0 => Vsync::Adaptive,
+            _ => Vsync::On,
+        })
+    }
+
+    #[cfg(feature = "gl")]
+    fn pixel_format(&self) -> GlConfig {
+        self.pixel_format
+    }
+
+    #[cfg(feature = "gl")]
+    fn proc_address(&self, name: &CStr) -> *const c_void {
+        let name = name.as_ptr() as _;
+
+        // `wglGetProcAddress` only knows about extensions, and some drivers return small
+        // sentinel values instead of null. OpenGL 1.1 entry points live in opengl32.dll itself.
+        let address = match unsafe { wglGetProcAddress(name) } {
+            Some(proc) if !matches!(proc as usize, 1 | 2 | 3 | usize::MAX) => Some(proc),
+            _ => unsafe { GetProcAddress(GetModuleHandleA(c"opengl32.dll".as_ptr() as _), name) },
+        };
+
+        address.map_or(ptr::null(), |proc| proc as _)
+    }
 }
 
-impl Window {
-    pub fn new(name: &CStr) -> Self {
-        let instance = unsafe { GetModuleHandleA(ptr::null()) };
-        assert!(instance != 0);
-
-        let class = WNDCLASSA {
-            style: CS_HREDRAW | CS_VREDRAW,
-            lpfnWndProc: Some(message_callback),
-            hInstance: instance,
-            hCursor: unsafe { LoadCursorA(0, IDC_ARROW as _) },
-            lpszClassName: c"window".as_ptr() as _,
+impl Drop for Window {
+    fn drop(&mut self) {
+        // Before the DC it's current on goes away.
+        #[cfg(feature = "gl")]
+        drop(self.context.take());
+
+        if self.state.exclusive.get().is_some() {
+            change_display_mode(None);
+        }
+
+        unsafe { ReleaseDC(self.hwnd, self.hdc); };
+
+        // Not closed by the application, or bailing out of `build`. Detached first so nobody hears
+        // about it.
+        if !self.state.destroyed.get() {
+            unsafe { SetWindowLongPtrA(self.hwnd, GWLP_USERDATA, 0) };
+            unsafe { DestroyWindow(self.hwnd) };
+
+            thread::destroyed();
+        }
+
+        thread::forget(self.id());
+    }
+}
+
+extern "system" fn message_callback(hwnd: HWND, message: u32, w: WPARAM, l: LPARAM) -> LRESULT {
+    let state = unsafe { (GetWindowLongPtrA(hwnd, GWLP_USERDATA) as *const State).as_ref() };
+
+    match (state, message) {
+        (None, WM_NCCREATE) => {
+            let create = unsafe { &*(l as *const CREATESTRUCTW) };
+            let state = unsafe { &*(create.lpCreateParams as *const State) };
+
+            state.hwnd.set(hwnd);
+            thread::opened();
+
+            unsafe { SetWindowLongPtrA(hwnd, GWLP_USERDATA, state as *const State as _) };
+            unsafe { DefWindowProcW(hwnd, message, w, l) }
+        }
+
+        // WM_SYS* are sent for Alt and F10, and must still reach DefWindowProcW for Alt+F4 and
+        // the window menu to work.
+        (Some(state), WM_KEYDOWN | WM_SYSKEYDOWN) => {
+            let (key, physical, scancode, extended) = key(w as _, l);
+            let repeat = l & 1 << 30 != 0;
+
+            if !repeat || state.key_repeat.get() {
+                state.emit(Event::Press {
+                    key,
+                    physical,
+                    scancode,
+                    extended,
+                    repeat,
+                    modifiers: modifiers(),
+                });
+            }
+
+            match message {
+                WM_SYSKEYDOWN => unsafe { DefWindowProcW(hwnd, message, w, l) },
+                _ => 0,
+            }
+        }
+
+        (Some(state), WM_KEYUP | WM_SYSKEYUP) => {
+            let (key, physical, scancode, extended) = key(w as _, l);
+            state.emit(Event::Release {
+                key,
+                physical,
+                scancode,
+                extended,
+                modifiers: modifiers(),
+            });
+
+            match message {
+                WM_SYSKEYUP => unsafe { DefWindowProcW(hwnd, message, w, l) },
+                _ => 0,
+            }
+        }
+
+        (Some(state), WM_CHAR) => {
+            if let Some(c) = character(state, w as u16) {
+                state.emit(Event::Text(c));
+            }
+
+            0
+        }
+
+        (Some(state), WM_MOUSEMOVE) => {
+            let x = l as u16 as i16;
+            let y = (l >> 16) as u16 as i16;
+
+            state.emit(Event::Motion {
+                position: [x as _, y as _],
+                modifiers: modifiers(),
+            });
+            0
+        }
+
+        (Some(state), WM_LBUTTONDOWN | WM_RBUTTONDOWN | WM_MBUTTONDOWN | WM_XBUTTONDOWN) => {
+            state.emit(Event::ButtonPress {
+                button: button(message, w),
+                modifiers: modifiers(),
+            });
+            (message == WM_XBUTTONDOWN) as _
+        }
+
+        (Some(state), WM_LBUTTONUP | WM_RBUTTONUP | WM_MBUTTONUP | WM_XBUTTONUP) => {
+            state.emit(Event::ButtonRelease {
+                button: button(message, w),
+                modifiers: modifiers(),
+            });
+            (message == WM_XBUTTONUP) as _
+        }
+
+        (Some(state), WM_MOUSEWHEEL) => {
+            let delta = (w >> 16) as u16 as i16;
+
+            state.emit(Event::Wheel {
+                delta: delta as f32 / WHEEL_DELTA as f32,
+                modifiers: modifiers(),
+            });
+            0
+        }
+
+        (Some(state), _) if message != 0 && message == MSH_MOUSEWHEEL.load(Ordering::Relaxed) => {
+            let delta = w as i32;
+
+            state.emit(Event::Wheel {
+                delta: delta as f32 / WHEEL_DELTA as f32,
+                modifiers: modifiers(),
+            });
+            0
+        }
+
+        (Some(state), WM_SIZE) if w == SIZE_MINIMIZED as _ => {
+            state.minimized.set(true);
+            state.emit(Event::Minimized);
+            0
+        }
+
+        (Some(state), WM_SIZE) => {
+            if state.minimized.replace(false) {
+                state.emit(Event::Restored);
+            }
+
+            let width = l as u16;
+            let height = (l >> 16) as u16;
+
+            state.emit(Event::Resize([width as _, height as _]));
+            0
+        }
+
+        (Some(state), WM_GETMINMAXINFO) => {
+            let info = unsafe { &mut *(l as *mut MINMAXINFO) };
+
+            if let Some([x, y]) = state.min_size {
+                info.ptMinTrackSize = POINT { x, y };
+            }
+
+            if let Some([x, y]) = state.max_size {
+                info.ptMaxTrackSize = POINT { x, y };
+            }
+
+            0
+        }
+
+        // Give the desktop back while the user is away, e.g. after Alt+Tab.
+        (Some(state), WM_ACTIVATEAPP) => {
+            if let Some(mode) = state.exclusive.get() {
+                match w != 0 {
+                    true => {
+                        change_display_mode(Some(mode));
+                        unsafe { ShowWindow(hwnd, SW_RESTORE) };
+                    }
+
+                    false => {
+                        change_display_mode(None);
+                        unsafe { ShowWindow(hwnd, SW_MINIMIZE) };
+                    }
+                }
+            }
+
+            0
+        }
+
+        (Some(state), WM_SETFOCUS | WM_KILLFOCUS) => {
+            state.emit(Event::Focus(message == WM_SETFOCUS));
+            0
+        }
+
+        // Closing is up to the application, which may call `close` in response.
+        (Some(state), WM_CLOSE) => {
+            state.emit(Event::CloseRequested);
+            0
+        }
+
+        (Some(state), WM_DESTROY) => {
+            state.destroyed.set(true);
+            thread::destroyed();
+
+            state.emit(Event::Destroyed);
+            unsafe { SetWindowLongPtrA(hwnd, GWLP_USERDATA, 0) };
+            0
+        }
+
+        _ => unsafe { DefWindowProcW(hwnd, message, w, l) },
+    }
+}
+
+// WM_CHAR carries UTF-16 code units, characters outside the BMP take a message for each half.
+fn character(state: &State, unit: u16) -> Option<char> {
+    match state.high_surrogate.take() {
+        // A high surrogate left unpaired is dropped, whatever follows it isn't.
+        Some(high) => char::decode_utf16([high, unit]).find_map(Result::ok),
+        None if (0xD800..0xDC00).contains(&unit) => {
+            state.high_surrogate.set(Some(unit));
+            None
+        }
+        None => char::from_u32(unit as _),
+    }
+}
+
+// Turns a failed call into an `Error` carrying `GetLastError`.
+fn check(success: bool, function: &'static str) -> Result<(), Error> {
+    match success {
+        true => Ok(()),
+        false => Err(Error {
+            function,
+            code: unsafe { GetLastError() },
+        }),
+    }
+}
+
+// Tries each configuration in turn, settling for the nearest match to the first one if none is fully
+// provided. The generic implementation is software-only GL 1.1 and doesn't count as providing it.
+#[cfg(feature = "gl")]
+fn choose_pixel_format(
+    hdc: HDC,
+    configs: &[GlConfig],
+) -> Result<(i32, PIXELFORMATDESCRIPTOR), Error> {
+    let mut nearest = None;
+
+    for config in configs {
+        let buffering = match config.double_buffer {
+            true => PFD_DOUBLEBUFFER,
+            false => 0,
+        };
+
+        let wanted = PIXELFORMATDESCRIPTOR {
+            nSize: mem::size_of::<PIXELFORMATDESCRIPTOR>() as _,
+            nVersion: 1,
+            dwFlags: PFD_DRAW_TO_WINDOW | PFD_SUPPORT_OPENGL | buffering,
+            iPixelType: PFD_TYPE_RGBA,
+            cColorBits: config.color_bits,
+            cAlphaBits: config.alpha_bits,
+            cDepthBits: config.depth_bits,
+            cStencilBits: config.stencil_bits,
             ..unsafe { mem::zeroed() }
         };
 
-        let atom = unsafe { RegisterClassA(&class) };
-        assert!(atom != 0);
+        let idx = unsafe { ChoosePixelFormat(hdc, &wanted) };
+
+        if idx == 0 {
+            continue;
+        }
+
+        let desc = describe_pixel_format(hdc, idx);
+        let generic = desc.dwFlags & (PFD_GENERIC_FORMAT | PFD_GENERIC_ACCELERATED);
+
+        if generic != PFD_GENERIC_FORMAT && gl_config(&desc).satisfies(config) {
+            return Ok((idx, desc));
+        }
+
+        nearest = nearest.or(Some((idx, desc)));
+    }
+
+    nearest.ok_or_else(|| Error {
+        function: "ChoosePixelFormat",
+        code: unsafe { GetLastError() },
+    })
+}
+
+#[cfg(feature = "gl")]
+fn describe_pixel_format(hdc: HDC, idx: i32) -> PIXELFORMATDESCRIPTOR {
+    let mut desc = unsafe { mem::zeroed::<PIXELFORMATDESCRIPTOR>() };
+    let size = mem::size_of_val(&desc) as _;
+
+    unsafe { DescribePixelFormat(hdc, idx, size, &mut desc) };
+    desc
+}
+
+// Multisampling and sRGB only come through WGL_ARB_pixel_format.
+#[cfg(feature = "gl")]
+fn gl_config(desc: &PIXELFORMATDESCRIPTOR) -> GlConfig {
+    GlConfig {
+        color_bits: desc.cColorBits,
+        alpha_bits: desc.cAlphaBits,
+        depth_bits: desc.cDepthBits,
+        stencil_bits: desc.cStencilBits,
+        samples: 0,
+        double_buffer: desc.dwFlags & PFD_DOUBLEBUFFER != 0,
+        srgb: false,
+    }
+}
+
+// Entry points from WGL_ARB_pixel_format and WGL_ARB_create_context, any of which may be missing.
+#[cfg(feature = "gl")]
+#[derive(Clone, Copy, Default)]
+struct Arb {
+    choose_pixel_format: Option<
+        unsafe extern "system" fn(HDC, *const i32, *const f32, u32, *mut i32, *mut u32) -> BOOL,
+    >,
+    get_pixel_format_attrib:
+        Option<unsafe extern "system" fn(HDC, i32, i32, u32, *const i32, *mut i32) -> BOOL>,
+    create_context: Option<unsafe extern "system" fn(HDC, HGLRC, *const i32) -> HGLRC>,
+}
 
+#[cfg(feature = "gl")]
+impl Arb {
+    // `wglGetProcAddress` needs a current context, and a window's pixel format can only be set
+    // once, so the entry points are looked up through a throwaway window of a system class.
+    fn load() -> Self {
+        let class = c"STATIC".as_ptr() as _;
         let hwnd = unsafe {
             CreateWindowExA(
                 0,
-                class.lpszClassName,
-                name.as_ptr() as _,
-                WS_OVERLAPPEDWINDOW | WS_VISIBLE,
-                CW_USEDEFAULT,
-                CW_USEDEFAULT,
-                CW_USEDEFAULT,
-                CW_USEDEFAULT,
+                class,
+                ptr::null(),
+                WS_POPUP,
+                0,
+                0,
+                1,
+                1,
+                0,
                 0,
                 0,
-                instance,
                 ptr::null(),
             )
         };
 
-        assert!(hwnd != 0);
+        if hwnd == 0 {
+            return Self::default();
+        }
 
         let hdc = unsafe { GetDC(hwnd) };
-        assert!(hdc != 0);
-
-        #[cfg(feature = "gl")]
-        {
-            let desc = PIXELFORMATDESCRIPTOR {
-                nSize: mem::size_of::<PIXELFORMATDESCRIPTOR>() as _,
-                nVersion: 1,
-                dwFlags: PFD_DRAW_TO_WINDOW | PFD_SUPPORT_OPENGL | PFD_DOUBLEBUFFER,
-                iPixelType: PFD_TYPE_RGBA,
-                cColorBits: 24,
-                cDepthBits: 32,
-                ..unsafe { mem::zeroed() }
-            };
-
-            let idx = unsafe { ChoosePixelFormat(hdc, &desc) };
-            assert!(idx != 0);
 
-            let success = unsafe { SetPixelFormat(hdc, idx, &desc) };
-            assert!(success != 0);
+        if let Ok((idx, desc)) = choose_pixel_format(hdc, &[GlConfig::DEFAULT]) {
+            unsafe { SetPixelFormat(hdc, idx, &desc) };
         }
 
-        #[cfg(feature = "gl")]
         let hglrc = unsafe { wglCreateContext(hdc) };
+        unsafe { wglMakeCurrent(hdc, hglrc) };
 
-        #[cfg(feature = "gl")]
-        assert!(hglrc != 0);
+        // Null without a current context, leaving everything missing.
+        let arb = unsafe {
+            Self {
+                choose_pixel_format: crate::wgl_load!(
+                    c"wglChoosePixelFormatARB",
+                    fn(HDC, *const i32, *const f32, u32, *mut i32, *mut u32) -> BOOL
+                ),
 
-        unsafe { wglMakeCurrent(hdc, hglrc) };
+                get_pixel_format_attrib: crate::wgl_load!(
+                    c"wglGetPixelFormatAttribivARB",
+                    fn(HDC, i32, i32, u32, *const i32, *mut i32) -> BOOL
+                ),
 
-        Self {
-            hwnd,
-            hdc,
+                create_context: crate::wgl_load!(
+                    c"wglCreateContextAttribsARB",
+                    fn(HDC, HGLRC, *const i32) -> HGLRC
+                ),
+            }
+        };
 
-            #[cfg(feature = "gl")]
-            hglrc,
+        unsafe { wglMakeCurrent(0, 0) };
+        unsafe { wglDeleteContext(hglrc) };
+        unsafe { ReleaseDC(hwnd, hdc) };
+        unsafe { DestroyWindow(hwnd) };
+
+        arb
+    }
+
+    // Unlike `ChoosePixelFormat`, only formats meeting every minimum are returned, so the first
+    // configuration with any is the one.
+    fn choose_pixel_format(
+        &self,
+        hdc: HDC,
+        configs: &[GlConfig],
+    ) -> Option<(i32, PIXELFORMATDESCRIPTOR)> {
+        let choose = self.choose_pixel_format?;
+
+        for config in configs {
+            #[rustfmt::skip]
+            let mut attributes = vec![
+                arb::DRAW_TO_WINDOW, 1,
+                arb::SUPPORT_OPENGL, 1,
+                arb::ACCELERATION, arb::FULL_ACCELERATION,
+                arb::PIXEL_TYPE, arb::TYPE_RGBA,
+                arb::DOUBLE_BUFFER, config.double_buffer as _,
+                arb::COLOR_BITS, config.color_bits as _,
+                arb::ALPHA_BITS, config.alpha_bits as _,
+                arb::DEPTH_BITS, config.depth_bits as _,
+                arb::STENCIL_BITS, config.stencil_bits as _,
+            ];
+
+            if config.samples > 0 {
+                attributes.extend([arb::SAMPLE_BUFFERS, 1, arb::SAMPLES, config.samples as _]);
+            }
+
+            if config.srgb {
+                attributes.extend([arb::FRAMEBUFFER_SRGB_CAPABLE, 1]);
+            }
+
+            attributes.push(0);
+
+            let (mut idx, mut count) = (0, 0);
+            let success = unsafe {
+                choose(
+                    hdc,
+                    attributes.as_ptr(),
+                    ptr::null(),
+                    1,
+                    &mut idx,
+                    &mut count,
+                )
+            };
+
+            if success != 0 && count > 0 {
+                return Some((idx, describe_pixel_format(hdc, idx)));
+            }
         }
+
+        None
     }
 
-    pub fn inner_size(&self) -> [u32; 2] {
-        let mut rect = unsafe { mem::zeroed() };
+    fn describe_pixel_format(&self, hdc: HDC, idx: i32) -> Option<GlConfig> {
+        let get_attrib = self.get_pixel_format_attrib?;
 
-        let success = unsafe { GetClientRect(self.hwnd, &mut rect) };
-        assert!(success != 0);
+        // One at a time, asking for an attribute from a missing extension fails the whole call.
+        let get = |attribute| {
+            let mut value = 0;
+            unsafe { get_attrib(hdc, idx, 0, 1, &attribute, &mut value) };
+            value
+        };
 
-        let width = rect.right - rect.left;
-        let height = rect.bottom - rect.top;
+        Some(GlConfig {
+            color_bits: get(arb::COLOR_BITS) as _,
+            alpha_bits: get(arb::ALPHA_BITS) as _,
+            depth_bits: get(arb::DEPTH_BITS) as _,
+            stencil_bits: get(arb::STENCIL_BITS) as _,
+            samples: get(arb::SAMPLES) as _,
+            double_buffer: get(arb::DOUBLE_BUFFER) != 0,
+            srgb: get(arb::FRAMEBUFFER_SRGB_CAPABLE) != 0,
+        })
+    }
 
-        [width as _, height as _]
+    // `None` without the extension, a null context if the driver turns the attributes down.
+    fn create_context(&self, hdc: HDC, attributes: GlAttributes, share: HGLRC) -> Option<HGLRC> {
+        let create = self.create_context?;
+        let [major, minor] = attributes.version;
+
+        let profile = match attributes.profile {
+            GlProfile::Core => arb::CONTEXT_CORE_PROFILE_BIT,
+            GlProfile::Compatibility => arb::CONTEXT_COMPATIBILITY_PROFILE_BIT,
+        };
+
+        let flags = match attributes.debug {
+            true => arb::CONTEXT_DEBUG_BIT,
+            false => 0,
+        };
+
+        #[rustfmt::skip]
+        let attributes = [
+            arb::CONTEXT_MAJOR_VERSION, major as _,
+            arb::CONTEXT_MINOR_VERSION, minor as _,
+            arb::CONTEXT_PROFILE_MASK, profile,
+            arb::CONTEXT_FLAGS, flags,
+            0,
+        ];
+
+        Some(unsafe { create(hdc, share, attributes.as_ptr()) })
     }
+}
 
-    pub fn event_loop(&self, mut cb: impl FnMut(Event)) {
-        let mut message = unsafe { mem::zeroed::<MSG>() };
-        let fat_pointer = &mut cb as *mut dyn FnMut(Event);
-        let user_pointer = &fat_pointer as *const _;
+// From the WGL_ARB_pixel_format, WGL_ARB_multisample, WGL_ARB_framebuffer_sRGB and
+// WGL_ARB_create_context specifications.
+#[cfg(feature = "gl")]
+mod arb {
+    pub const DRAW_TO_WINDOW: i32 = 0x2001;
+    pub const ACCELERATION: i32 = 0x2003;
+    pub const SUPPORT_OPENGL: i32 = 0x2010;
+    pub const DOUBLE_BUFFER: i32 = 0x2011;
+    pub const PIXEL_TYPE: i32 = 0x2013;
+    pub const COLOR_BITS: i32 = 0x2014;
+    pub const ALPHA_BITS: i32 = 0x201B;
+    pub const DEPTH_BITS: i32 = 0x2022;
+    pub const STENCIL_BITS: i32 = 0x2023;
+    pub const FULL_ACCELERATION: i32 = 0x2027;
+    pub const TYPE_RGBA: i32 = 0x202B;
+    pub const SAMPLE_BUFFERS: i32 = 0x2041;
+    pub const SAMPLES: i32 = 0x2042;
+    pub const FRAMEBUFFER_SRGB_CAPABLE: i32 = 0x20A9;
 
-        unsafe { SetWindowLongPtrA(self.hwnd, GWLP_USERDATA, user_pointer as _) };
+    pub const CONTEXT_MAJOR_VERSION: i32 = 0x2091;
+    pub const CONTEXT_MINOR_VERSION: i32 = 0x2092;
+    pub const CONTEXT_FLAGS: i32 = 0x2094;
+    pub const CONTEXT_PROFILE_MASK: i32 = 0x9126;
+    pub const CONTEXT_DEBUG_BIT: i32 = 0x0001;
+    pub const CONTEXT_CORE_PROFILE_BIT: i32 = 0x0001;
+    pub const CONTEXT_COMPATIBILITY_PROFILE_BIT: i32 = 0x0002;
+}
 
-        while message.message != WM_QUIT {
-            if unsafe { PeekMessageA(&mut message, 0, 0, 0, PM_REMOVE) } == 0 {
-                cb(Event::Idle);
-                continue;
-            }
+// Runs the window procedure for every message waiting, which queues whatever they report.
+fn pump() {
+    let mut message = unsafe { mem::zeroed::<MSG>() };
 
-            unsafe { DispatchMessageA(&message) };
-        }
+    while unsafe { PeekMessageW(&mut message, 0, 0, 0, PM_REMOVE) } != 0 {
+        unsafe { TranslateMessage(&message) };
+        unsafe { DispatchMessageW(&message) };
     }
 }
 
-impl Drop for Window {
-    fn drop(&mut self) {
-        #[cfg(feature = "gl")]
-        unsafe {
-            wglMakeCurrent(0, 0);
-            wglDeleteContext(self.hglrc);
+// Sleeps until a message arrives, or `Poll` or the deadline say otherwise.
+fn wait(flow: ControlFlow) {
+    match flow {
+        ControlFlow::Poll | ControlFlow::Exit => {}
+        ControlFlow::Wait => {
+            unsafe { WaitMessage() };
         }
 
-        unsafe { ReleaseDC(self.hwnd, self.hdc); };
+        ControlFlow::WaitUntil(deadline) => {
+            // Rounded up, waking before the deadline would only mean waiting again. One short of
+            // `INFINITE`, which would never wake.
+            let left = deadline.saturating_duration_since(Instant::now());
+            let timeout = left.as_micros().div_ceil(1000).min(u32::MAX as u128 - 1) as u32;
+
+            unsafe { MsgWaitForMultipleObjects(0, ptr::null(), 0, timeout, QS_ALLINPUT) };
+        }
     }
 }
 
-extern "system" fn message_callback(hwnd: HWND, message: u32, w: WPARAM, l: LPARAM) -> LRESULT {
-    type UserPtr = *const *mut dyn FnMut(Event);
-    let user_pointer = unsafe { GetWindowLongPtrA(hwnd, GWLP_USERDATA) } as UserPtr;
-    let callback = unsafe { user_pointer.as_ref() };
+// Converts to the active code page, which is what the ANSI API takes.
+fn to_ansi(s: &str) -> Vec<u8> {
+    // Anything past a nul would be cut off anyway.
+    let wide: Vec<u16> = s
+        .split('\0')
+        .next()
+        .unwrap_or_default()
+        .encode_utf16()
+        .collect();
 
-    match (callback, message) {
-        (Some(cb), WM_KEYDOWN) => {
-            unsafe { (**cb)(Event::Press(w as _)) };
-            0
-        }
+    let len = unsafe {
+        WideCharToMultiByte(
+            CP_ACP,
+            0,
+            wide.as_ptr(),
+            wide.len() as _,
+            ptr::null_mut(),
+            0,
+            ptr::null(),
+            ptr::null_mut(),
+        )
+    };
 
-        (Some(cb), WM_KEYUP) => {
-            unsafe { (**cb)(Event::Release(w as _)) };
-            0
-        }
+    let mut bytes = vec![0; len as usize];
 
-        (_, WM_CLOSE) => {
-            unsafe { DestroyWindow(hwnd) };
-            0
-        }
+    unsafe {
+        WideCharToMultiByte(
+            CP_ACP,
+            0,
+            wide.as_ptr(),
+            wide.len() as _,
+            bytes.as_mut_ptr(),
+            len,
+            ptr::null(),
+            ptr::null_mut(),
+        )
+    };
 
-        (_, WM_DESTROY) => {
-            unsafe { PostQuitMessage(0) };
-            0
+    bytes
+}
+
+fn from_ansi(bytes: &[u8]) -> String {
+    let len = unsafe {
+        MultiByteToWideChar(
+            CP_ACP,
+            0,
+            bytes.as_ptr(),
+            bytes.len() as _,
+            ptr::null_mut(),
+            0,
+        )
+    };
+
+    let mut wide = vec![0; len as usize];
+
+    let len = unsafe {
+        MultiByteToWideChar(
+            CP_ACP,
+            0,
+            bytes.as_ptr(),
+            bytes.len() as _,
+            wide.as_mut_ptr(),
+            len,
+        )
+    };
+
+    String::from_utf16_lossy(&wide[..len as usize])
+}
+
+// Switches the primary display to `mode`, or back to the registry one with `None`.
+fn change_display_mode(mode: Option<DisplayMode>) -> bool {
+    // Aborting panics never run `Drop`, and unwinding ones only after the message is reported on a
+    // screen the user may not be able to read, so switch back first.
+    PANIC_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+
+        panic::set_hook(Box::new(move |info| {
+            if MODE_CHANGED.swap(false, Ordering::Relaxed) {
+                unsafe { ChangeDisplaySettingsA(ptr::null(), 0) };
+            }
+
+            previous(info);
+        }));
+    });
+
+    let result = match mode {
+        Some(mode) => {
+            let [width, height] = mode.size;
+
+            let mut devmode = DEVMODEA {
+                dmSize: mem::size_of::<DEVMODEA>() as _,
+                dmFields: DM_PELSWIDTH | DM_PELSHEIGHT | DM_BITSPERPEL,
+                dmPelsWidth: width,
+                dmPelsHeight: height,
+                dmBitsPerPel: mode.bits_per_pixel,
+                ..unsafe { mem::zeroed() }
+            };
+
+            if mode.refresh_rate > 1 {
+                devmode.dmFields |= DM_DISPLAYFREQUENCY;
+                devmode.dmDisplayFrequency = mode.refresh_rate;
+            }
+
+            unsafe { ChangeDisplaySettingsA(&devmode, CDS_FULLSCREEN) }
         }
 
-        _ => unsafe { DefWindowProcA(hwnd, message, w, l) },
+        None => unsafe { ChangeDisplaySettingsA(ptr::null(), 0) },
+    };
+
+    let success = result == DISP_CHANGE_SUCCESSFUL;
+
+    if success {
+        MODE_CHANGED.store(mode.is_some(), Ordering::Relaxed);
+    }
+
+    success
+}
+
+// Outer size of a window with the given client area size and style.
+fn outer_size(size: [u32; 2], style: u32) -> [i32; 2] {
+    let [width, height] = size;
+
+    let mut rect = RECT {
+        left: 0,
+        top: 0,
+        right: width as _,
+        bottom: height as _,
+    };
+
+    unsafe { AdjustWindowRect(&mut rect, style, 0) };
+    [rect.right - rect.left, rect.bottom - rect.top]
+}
+
+fn button(message: u32, w: WPARAM) -> Button {
+    match message {
+        WM_LBUTTONDOWN | WM_LBUTTONUP => Button::Left,
+        WM_RBUTTONDOWN | WM_RBUTTONUP => Button::Right,
+        WM_MBUTTONDOWN | WM_MBUTTONUP => Button::Middle,
+        _ if (w >> 16) as u16 == XBUTTON1 => Button::X1,
+        _ => Button::X2,
     }
 }
 
+// `GetKeyState` reflects the keyboard as of the message being handled, not as of right now.
+fn modifiers() -> Modifiers {
+    let down = |vk| unsafe { km::GetKeyState(vk as _) } < 0;
+    let toggled = |vk| unsafe { km::GetKeyState(vk as _) } & 1 != 0;
+
+    let mut modifiers = Modifiers::empty();
+
+    modifiers.set(Modifiers::SHIFT, down(km::VK_SHIFT));
+    modifiers.set(Modifiers::CTRL, down(km::VK_CONTROL));
+    modifiers.set(Modifiers::ALT, down(km::VK_MENU));
+    modifiers.set(Modifiers::LOGO, down(km::VK_LWIN) || down(km::VK_RWIN));
+    modifiers.set(Modifiers::CAPS_LOCK, toggled(km::VK_CAPITAL));
+    modifiers.set(Modifiers::NUM_LOCK, toggled(km::VK_NUMLOCK));
+    modifiers
+}
+
 #[cfg(feature = "gl")]
 #[macro_export]
 macro_rules! wgl_load {
@@ -191,253 +1575,175 @@ macro_rules! wgl_load {
     }};
 }
 
-pub mod key {
-    use windows_sys::Win32::UI::Input::KeyboardAndMouse as km;
-
-    pub const LBUTTON: usize = km::VK_LBUTTON as _;
-    pub const RBUTTON: usize = km::VK_RBUTTON as _;
-    pub const CANCEL: usize = km::VK_CANCEL as _;
-    pub const MBUTTON: usize = km::VK_MBUTTON as _;
-    pub const XBUTTON1: usize = km::VK_XBUTTON1 as _;
-    pub const XBUTTON2: usize = km::VK_XBUTTON2 as _;
-    pub const BACK: usize = km::VK_BACK as _;
-    pub const TAB: usize = km::VK_TAB as _;
-    pub const CLEAR: usize = km::VK_CLEAR as _;
-    pub const RETURN: usize = km::VK_RETURN as _;
-    pub const SHIFT: usize = km::VK_SHIFT as _;
-    pub const CONTROL: usize = km::VK_CONTROL as _;
-    pub const MENU: usize = km::VK_MENU as _;
-    pub const PAUSE: usize = km::VK_PAUSE as _;
-    pub const CAPITAL: usize = km::VK_CAPITAL as _;
-    pub const HANGEUL: usize = km::VK_HANGEUL as _;
-    pub const HANGUL: usize = km::VK_HANGUL as _;
-    pub const KANA: usize = km::VK_KANA as _;
-    pub const IME_ON: usize = km::VK_IME_ON as _;
-    pub const JUNJA: usize = km::VK_JUNJA as _;
-    pub const FINAL: usize = km::VK_FINAL as _;
-    pub const HANJA: usize = km::VK_HANJA as _;
-    pub const KANJI: usize = km::VK_KANJI as _;
-    pub const IME_OFF: usize = km::VK_IME_OFF as _;
-    pub const ESCAPE: usize = km::VK_ESCAPE as _;
-    pub const CONVERT: usize = km::VK_CONVERT as _;
-    pub const NONCONVERT: usize = km::VK_NONCONVERT as _;
-    pub const ACCEPT: usize = km::VK_ACCEPT as _;
-    pub const MODECHANGE: usize = km::VK_MODECHANGE as _;
-    pub const SPACE: usize = km::VK_SPACE as _;
-    pub const PRIOR: usize = km::VK_PRIOR as _;
-    pub const NEXT: usize = km::VK_NEXT as _;
-    pub const END: usize = km::VK_END as _;
-    pub const HOME: usize = km::VK_HOME as _;
-    pub const LEFT: usize = km::VK_LEFT as _;
-    pub const UP: usize = km::VK_UP as _;
-    pub const RIGHT: usize = km::VK_RIGHT as _;
-    pub const DOWN: usize = km::VK_DOWN as _;
-    pub const SELECT: usize = km::VK_SELECT as _;
-    pub const PRINT: usize = km::VK_PRINT as _;
-    pub const EXECUTE: usize = km::VK_EXECUTE as _;
-    pub const SNAPSHOT: usize = km::VK_SNAPSHOT as _;
-    pub const INSERT: usize = km::VK_INSERT as _;
-    pub const DELETE: usize = km::VK_DELETE as _;
-    pub const HELP: usize = km::VK_HELP as _;
-    pub const NUM0: usize = km::VK_0 as _;
-    pub const NUM1: usize = km::VK_1 as _;
-    pub const NUM2: usize = km::VK_2 as _;
-    pub const NUM3: usize = km::VK_3 as _;
-    pub const NUM4: usize = km::VK_4 as _;
-    pub const NUM5: usize = km::VK_5 as _;
-    pub const NUM6: usize = km::VK_6 as _;
-    pub const NUM7: usize = km::VK_7 as _;
-    pub const NUM8: usize = km::VK_8 as _;
-    pub const NUM9: usize = km::VK_9 as _;
-    pub const A: usize = km::VK_A as _;
-    pub const B: usize = km::VK_B as _;
-    pub const C: usize = km::VK_C as _;
-    pub const D: usize = km::VK_D as _;
-    pub const E: usize = km::VK_E as _;
-    pub const F: usize = km::VK_F as _;
-    pub const G: usize = km::VK_G as _;
-    pub const H: usize = km::VK_H as _;
-    pub const I: usize = km::VK_I as _;
-    pub const J: usize = km::VK_J as _;
-    pub const K: usize = km::VK_K as _;
-    pub const L: usize = km::VK_L as _;
-    pub const M: usize = km::VK_M as _;
-    pub const N: usize = km::VK_N as _;
-    pub const O: usize = km::VK_O as _;
-    pub const P: usize = km::VK_P as _;
-    pub const Q: usize = km::VK_Q as _;
-    pub const R: usize = km::VK_R as _;
-    pub const S: usize = km::VK_S as _;
-    pub const T: usize = km::VK_T as _;
-    pub const U: usize = km::VK_U as _;
-    pub const V: usize = km::VK_V as _;
-    pub const W: usize = km::VK_W as _;
-    pub const X: usize = km::VK_X as _;
-    pub const Y: usize = km::VK_Y as _;
-    pub const Z: usize = km::VK_Z as _;
-    pub const LWIN: usize = km::VK_LWIN as _;
-    pub const RWIN: usize = km::VK_RWIN as _;
-    pub const APPS: usize = km::VK_APPS as _;
-    pub const SLEEP: usize = km::VK_SLEEP as _;
-    pub const NUMPAD0: usize = km::VK_NUMPAD0 as _;
-    pub const NUMPAD1: usize = km::VK_NUMPAD1 as _;
-    pub const NUMPAD2: usize = km::VK_NUMPAD2 as _;
-    pub const NUMPAD3: usize = km::VK_NUMPAD3 as _;
-    pub const NUMPAD4: usize = km::VK_NUMPAD4 as _;
-    pub const NUMPAD5: usize = km::VK_NUMPAD5 as _;
-    pub const NUMPAD6: usize = km::VK_NUMPAD6 as _;
-    pub const NUMPAD7: usize = km::VK_NUMPAD7 as _;
-    pub const NUMPAD8: usize = km::VK_NUMPAD8 as _;
-    pub const NUMPAD9: usize = km::VK_NUMPAD9 as _;
-    pub const MULTIPLY: usize = km::VK_MULTIPLY as _;
-    pub const ADD: usize = km::VK_ADD as _;
-    pub const SEPARATOR: usize = km::VK_SEPARATOR as _;
-    pub const SUBTRACT: usize = km::VK_SUBTRACT as _;
-    pub const DECIMAL: usize = km::VK_DECIMAL as _;
-    pub const DIVIDE: usize = km::VK_DIVIDE as _;
-    pub const F1: usize = km::VK_F1 as _;
-    pub const F2: usize = km::VK_F2 as _;
-    pub const F3: usize = km::VK_F3 as _;
-    pub const F4: usize = km::VK_F4 as _;
-    pub const F5: usize = km::VK_F5 as _;
-    pub const F6: usize = km::VK_F6 as _;
-    pub const F7: usize = km::VK_F7 as _;
-    pub const F8: usize = km::VK_F8 as _;
-    pub const F9: usize = km::VK_F9 as _;
-    pub const F10: usize = km::VK_F10 as _;
-    pub const F11: usize = km::VK_F11 as _;
-    pub const F12: usize = km::VK_F12 as _;
-    pub const F13: usize = km::VK_F13 as _;
-    pub const F14: usize = km::VK_F14 as _;
-    pub const F15: usize = km::VK_F15 as _;
-    pub const F16: usize = km::VK_F16 as _;
-    pub const F17: usize = km::VK_F17 as _;
-    pub const F18: usize = km::VK_F18 as _;
-    pub const F19: usize = km::VK_F19 as _;
-    pub const F20: usize = km::VK_F20 as _;
-    pub const F21: usize = km::VK_F21 as _;
-    pub const F22: usize = km::VK_F22 as _;
-    pub const F23: usize = km::VK_F23 as _;
-    pub const F24: usize = km::VK_F24 as _;
-    pub const NAVIGATION_VIEW: usize = km::VK_NAVIGATION_VIEW as _;
-    pub const NAVIGATION_MENU: usize = km::VK_NAVIGATION_MENU as _;
-    pub const NAVIGATION_UP: usize = km::VK_NAVIGATION_UP as _;
-    pub const NAVIGATION_DOWN: usize = km::VK_NAVIGATION_DOWN as _;
-    pub const NAVIGATION_LEFT: usize = km::VK_NAVIGATION_LEFT as _;
-    pub const NAVIGATION_RIGHT: usize = km::VK_NAVIGATION_RIGHT as _;
-    pub const NAVIGATION_ACCEPT: usize = km::VK_NAVIGATION_ACCEPT as _;
-    pub const NAVIGATION_CANCEL: usize = km::VK_NAVIGATION_CANCEL as _;
-    pub const NUMLOCK: usize = km::VK_NUMLOCK as _;
-    pub const SCROLL: usize = km::VK_SCROLL as _;
-    pub const OEM_FJ_JISHO: usize = km::VK_OEM_FJ_JISHO as _;
-    pub const OEM_NEC_EQUAL: usize = km::VK_OEM_NEC_EQUAL as _;
-    pub const OEM_FJ_MASSHOU: usize = km::VK_OEM_FJ_MASSHOU as _;
-    pub const OEM_FJ_TOUROKU: usize = km::VK_OEM_FJ_TOUROKU as _;
-    pub const OEM_FJ_LOYA: usize = km::VK_OEM_FJ_LOYA as _;
-    pub const OEM_FJ_ROYA: usize = km::VK_OEM_FJ_ROYA as _;
-    pub const LSHIFT: usize = km::VK_LSHIFT as _;
-    pub const RSHIFT: usize = km::VK_RSHIFT as _;
-    pub const LCONTROL: usize = km::VK_LCONTROL as _;
-    pub const RCONTROL: usize = km::VK_RCONTROL as _;
-    pub const LMENU: usize = km::VK_LMENU as _;
-    pub const RMENU: usize = km::VK_RMENU as _;
-    pub const BROWSER_BACK: usize = km::VK_BROWSER_BACK as _;
-    pub const BROWSER_FORWARD: usize = km::VK_BROWSER_FORWARD as _;
-    pub const BROWSER_REFRESH: usize = km::VK_BROWSER_REFRESH as _;
-    pub const BROWSER_STOP: usize = km::VK_BROWSER_STOP as _;
-    pub const BROWSER_SEARCH: usize = km::VK_BROWSER_SEARCH as _;
-    pub const BROWSER_FAVORITES: usize = km::VK_BROWSER_FAVORITES as _;
-    pub const BROWSER_HOME: usize = km::VK_BROWSER_HOME as _;
-    pub const VOLUME_MUTE: usize = km::VK_VOLUME_MUTE as _;
-    pub const VOLUME_DOWN: usize = km::VK_VOLUME_DOWN as _;
-    pub const VOLUME_UP: usize = km::VK_VOLUME_UP as _;
-    pub const MEDIA_NEXT_TRACK: usize = km::VK_MEDIA_NEXT_TRACK as _;
-    pub const MEDIA_PREV_TRACK: usize = km::VK_MEDIA_PREV_TRACK as _;
-    pub const MEDIA_STOP: usize = km::VK_MEDIA_STOP as _;
-    pub const MEDIA_PLAY_PAUSE: usize = km::VK_MEDIA_PLAY_PAUSE as _;
-    pub const LAUNCH_MAIL: usize = km::VK_LAUNCH_MAIL as _;
-    pub const LAUNCH_MEDIA_SELECT: usize = km::VK_LAUNCH_MEDIA_SELECT as _;
-    pub const LAUNCH_APP1: usize = km::VK_LAUNCH_APP1 as _;
-    pub const LAUNCH_APP2: usize = km::VK_LAUNCH_APP2 as _;
-    pub const OEM_1: usize = km::VK_OEM_1 as _;
-    pub const OEM_PLUS: usize = km::VK_OEM_PLUS as _;
-    pub const OEM_COMMA: usize = km::VK_OEM_COMMA as _;
-    pub const OEM_MINUS: usize = km::VK_OEM_MINUS as _;
-    pub const OEM_PERIOD: usize = km::VK_OEM_PERIOD as _;
-    pub const OEM_2: usize = km::VK_OEM_2 as _;
-    pub const OEM_3: usize = km::VK_OEM_3 as _;
-    pub const ABNT_C1: usize = km::VK_ABNT_C1 as _;
-    pub const ABNT_C2: usize = km::VK_ABNT_C2 as _;
-    pub const GAMEPAD_A: usize = km::VK_GAMEPAD_A as _;
-    pub const GAMEPAD_B: usize = km::VK_GAMEPAD_B as _;
-    pub const GAMEPAD_X: usize = km::VK_GAMEPAD_X as _;
-    pub const GAMEPAD_Y: usize = km::VK_GAMEPAD_Y as _;
-    pub const GAMEPAD_RIGHT_SHOULDER: usize = km::VK_GAMEPAD_RIGHT_SHOULDER as _;
-    pub const GAMEPAD_LEFT_SHOULDER: usize = km::VK_GAMEPAD_LEFT_SHOULDER as _;
-    pub const GAMEPAD_LEFT_TRIGGER: usize = km::VK_GAMEPAD_LEFT_TRIGGER as _;
-    pub const GAMEPAD_RIGHT_TRIGGER: usize = km::VK_GAMEPAD_RIGHT_TRIGGER as _;
-    pub const GAMEPAD_DPAD_UP: usize = km::VK_GAMEPAD_DPAD_UP as _;
-    pub const GAMEPAD_DPAD_DOWN: usize = km::VK_GAMEPAD_DPAD_DOWN as _;
-    pub const GAMEPAD_DPAD_LEFT: usize = km::VK_GAMEPAD_DPAD_LEFT as _;
-    pub const GAMEPAD_DPAD_RIGHT: usize = km::VK_GAMEPAD_DPAD_RIGHT as _;
-    pub const GAMEPAD_MENU: usize = km::VK_GAMEPAD_MENU as _;
-    pub const GAMEPAD_VIEW: usize = km::VK_GAMEPAD_VIEW as _;
-    pub const GAMEPAD_LEFT_THUMBSTICK_BUTTON: usize = km::VK_GAMEPAD_LEFT_THUMBSTICK_BUTTON as _;
-    pub const GAMEPAD_RIGHT_THUMBSTICK_BUTTON: usize = km::VK_GAMEPAD_RIGHT_THUMBSTICK_BUTTON as _;
-    pub const GAMEPAD_LEFT_THUMBSTICK_UP: usize = km::VK_GAMEPAD_LEFT_THUMBSTICK_UP as _;
-    pub const GAMEPAD_LEFT_THUMBSTICK_DOWN: usize = km::VK_GAMEPAD_LEFT_THUMBSTICK_DOWN as _;
-    pub const GAMEPAD_LEFT_THUMBSTICK_RIGHT: usize = km::VK_GAMEPAD_LEFT_THUMBSTICK_RIGHT as _;
-    pub const GAMEPAD_LEFT_THUMBSTICK_LEFT: usize = km::VK_GAMEPAD_LEFT_THUMBSTICK_LEFT as _;
-    pub const GAMEPAD_RIGHT_THUMBSTICK_UP: usize = km::VK_GAMEPAD_RIGHT_THUMBSTICK_UP as _;
-    pub const GAMEPAD_RIGHT_THUMBSTICK_DOWN: usize = km::VK_GAMEPAD_RIGHT_THUMBSTICK_DOWN as _;
-    pub const GAMEPAD_RIGHT_THUMBSTICK_RIGHT: usize = km::VK_GAMEPAD_RIGHT_THUMBSTICK_RIGHT as _;
-    pub const GAMEPAD_RIGHT_THUMBSTICK_LEFT: usize = km::VK_GAMEPAD_RIGHT_THUMBSTICK_LEFT as _;
-    pub const OEM_4: usize = km::VK_OEM_4 as _;
-    pub const OEM_5: usize = km::VK_OEM_5 as _;
-    pub const OEM_6: usize = km::VK_OEM_6 as _;
-    pub const OEM_7: usize = km::VK_OEM_7 as _;
-    pub const OEM_8: usize = km::VK_OEM_8 as _;
-    pub const OEM_AX: usize = km::VK_OEM_AX as _;
-    pub const OEM_102: usize = km::VK_OEM_102 as _;
-    pub const ICO_HELP: usize = km::VK_ICO_HELP as _;
-    pub const ICO_00: usize = km::VK_ICO_00 as _;
-    pub const PROCESSKEY: usize = km::VK_PROCESSKEY as _;
-    pub const ICO_CLEAR: usize = km::VK_ICO_CLEAR as _;
-    pub const PACKET: usize = km::VK_PACKET as _;
-    pub const OEM_RESET: usize = km::VK_OEM_RESET as _;
-    pub const OEM_JUMP: usize = km::VK_OEM_JUMP as _;
-    pub const OEM_PA1: usize = km::VK_OEM_PA1 as _;
-    pub const OEM_PA2: usize = km::VK_OEM_PA2 as _;
-    pub const OEM_PA3: usize = km::VK_OEM_PA3 as _;
-    pub const OEM_WSCTRL: usize = km::VK_OEM_WSCTRL as _;
-    pub const OEM_CUSEL: usize = km::VK_OEM_CUSEL as _;
-    pub const DBE_ALPHANUMERIC: usize = km::VK_DBE_ALPHANUMERIC as _;
-    pub const OEM_ATTN: usize = km::VK_OEM_ATTN as _;
-    pub const DBE_KATAKANA: usize = km::VK_DBE_KATAKANA as _;
-    pub const OEM_FINISH: usize = km::VK_OEM_FINISH as _;
-    pub const DBE_HIRAGANA: usize = km::VK_DBE_HIRAGANA as _;
-    pub const OEM_COPY: usize = km::VK_OEM_COPY as _;
-    pub const DBE_SBCSCHAR: usize = km::VK_DBE_SBCSCHAR as _;
-    pub const OEM_AUTO: usize = km::VK_OEM_AUTO as _;
-    pub const DBE_DBCSCHAR: usize = km::VK_DBE_DBCSCHAR as _;
-    pub const OEM_ENLW: usize = km::VK_OEM_ENLW as _;
-    pub const DBE_ROMAN: usize = km::VK_DBE_ROMAN as _;
-    pub const OEM_BACKTAB: usize = km::VK_OEM_BACKTAB as _;
-    pub const ATTN: usize = km::VK_ATTN as _;
-    pub const DBE_NOROMAN: usize = km::VK_DBE_NOROMAN as _;
-    pub const CRSEL: usize = km::VK_CRSEL as _;
-    pub const DBE_ENTERWORDREGISTERMODE: usize = km::VK_DBE_ENTERWORDREGISTERMODE as _;
-    pub const DBE_ENTERIMECONFIGMODE: usize = km::VK_DBE_ENTERIMECONFIGMODE as _;
-    pub const EXSEL: usize = km::VK_EXSEL as _;
-    pub const DBE_FLUSHSTRING: usize = km::VK_DBE_FLUSHSTRING as _;
-    pub const EREOF: usize = km::VK_EREOF as _;
-    pub const DBE_CODEINPUT: usize = km::VK_DBE_CODEINPUT as _;
-    pub const PLAY: usize = km::VK_PLAY as _;
-    pub const DBE_NOCODEINPUT: usize = km::VK_DBE_NOCODEINPUT as _;
-    pub const ZOOM: usize = km::VK_ZOOM as _;
-    pub const DBE_DETERMINESTRING: usize = km::VK_DBE_DETERMINESTRING as _;
-    pub const NONAME: usize = km::VK_NONAME as _;
-    pub const DBE_ENTERDLGCONVERSIONMODE: usize = km::VK_DBE_ENTERDLGCONVERSIONMODE as _;
-    pub const PA1: usize = km::VK_PA1 as _;
-    pub const OEM_CLEAR: usize = km::VK_OEM_CLEAR as _;
+// Returns the logical key, physical key, scancode and extended flag of a keystroke.
+fn key(vk: u32, l: LPARAM) -> (Key, Key, u32, bool) {
+    let scancode = (l >> 16) as u32 & 0xFF;
+    let extended = l & 1 << 24 != 0;
+
+    let physical = Key::from_scancode(scancode, extended);
+
+    // Windows only reports which side Shift, Ctrl and Alt are through the scancode.
+    let key = match (vk as u16, extended) {
+        (km::VK_SHIFT, _) if scancode == 0x36 => Key::RShift,
+        (km::VK_SHIFT, _) => Key::LShift,
+        (km::VK_CONTROL, true) => Key::RControl,
+        (km::VK_CONTROL, false) => Key::LControl,
+        (km::VK_MENU, true) => Key::RAlt,
+        (km::VK_MENU, false) => Key::LAlt,
+        (km::VK_RETURN, true) => Key::NumpadEnter,
+        _ => logical_key(vk),
+    };
+
+    (key, physical, scancode, extended)
+}
+
+fn logical_key(vk: u32) -> Key {
+    match vk as u16 {
+        km::VK_BACK => Key::Backspace,
+        km::VK_TAB => Key::Tab,
+        km::VK_CLEAR => Key::Clear,
+        km::VK_RETURN => Key::Return,
+        km::VK_PAUSE => Key::Pause,
+        km::VK_CAPITAL => Key::CapsLock,
+        km::VK_KANA => Key::Kana,
+        km::VK_KANJI => Key::Kanji,
+        km::VK_ESCAPE => Key::Escape,
+        km::VK_CONVERT => Key::Convert,
+        km::VK_NONCONVERT => Key::NonConvert,
+        km::VK_SPACE => Key::Space,
+        km::VK_PRIOR => Key::PageUp,
+        km::VK_NEXT => Key::PageDown,
+        km::VK_END => Key::End,
+        km::VK_HOME => Key::Home,
+        km::VK_LEFT => Key::Left,
+        km::VK_UP => Key::Up,
+        km::VK_RIGHT => Key::Right,
+        km::VK_DOWN => Key::Down,
+        km::VK_SELECT => Key::Select,
+        km::VK_PRINT => Key::Print,
+        km::VK_EXECUTE => Key::Execute,
+        km::VK_SNAPSHOT => Key::PrintScreen,
+        km::VK_INSERT => Key::Insert,
+        km::VK_DELETE => Key::Delete,
+        km::VK_HELP => Key::Help,
+        km::VK_0 => Key::Digit0,
+        km::VK_1 => Key::Digit1,
+        km::VK_2 => Key::Digit2,
+        km::VK_3 => Key::Digit3,
+        km::VK_4 => Key::Digit4,
+        km::VK_5 => Key::Digit5,
+        km::VK_6 => Key::Digit6,
+        km::VK_7 => Key::Digit7,
+        km::VK_8 => Key::Digit8,
+        km::VK_9 => Key::Digit9,
+        km::VK_A => Key::A,
+        km::VK_B => Key::B,
+        km::VK_C => Key::C,
+        km::VK_D => Key::D,
+        km::VK_E => Key::E,
+        km::VK_F => Key::F,
+        km::VK_G => Key::G,
+        km::VK_H => Key::H,
+        km::VK_I => Key::I,
+        km::VK_J => Key::J,
+        km::VK_K => Key::K,
+        km::VK_L => Key::L,
+        km::VK_M => Key::M,
+        km::VK_N => Key::N,
+        km::VK_O => Key::O,
+        km::VK_P => Key::P,
+        km::VK_Q => Key::Q,
+        km::VK_R => Key::R,
+        km::VK_S => Key::S,
+        km::VK_T => Key::T,
+        km::VK_U => Key::U,
+        km::VK_V => Key::V,
+        km::VK_W => Key::W,
+        km::VK_X => Key::X,
+        km::VK_Y => Key::Y,
+        km::VK_Z => Key::Z,
+        km::VK_LWIN => Key::LSuper,
+        km::VK_RWIN => Key::RSuper,
+        km::VK_APPS => Key::Menu,
+        km::VK_SLEEP => Key::Sleep,
+        km::VK_NUMPAD0 => Key::Numpad0,
+        km::VK_NUMPAD1 => Key::Numpad1,
+        km::VK_NUMPAD2 => Key::Numpad2,
+        km::VK_NUMPAD3 => Key::Numpad3,
+        km::VK_NUMPAD4 => Key::Numpad4,
+        km::VK_NUMPAD5 => Key::Numpad5,
+        km::VK_NUMPAD6 => Key::Numpad6,
+        km::VK_NUMPAD7 => Key::Numpad7,
+        km::VK_NUMPAD8 => Key::Numpad8,
+        km::VK_NUMPAD9 => Key::Numpad9,
+        km::VK_MULTIPLY => Key::NumpadMultiply,
+        km::VK_ADD => Key::NumpadAdd,
+        km::VK_SEPARATOR => Key::NumpadSeparator,
+        km::VK_SUBTRACT => Key::NumpadSubtract,
+        km::VK_DECIMAL => Key::NumpadDecimal,
+        km::VK_DIVIDE => Key::NumpadDivide,
+        km::VK_F1 => Key::F1,
+        km::VK_F2 => Key::F2,
+        km::VK_F3 => Key::F3,
+        km::VK_F4 => Key::F4,
+        km::VK_F5 => Key::F5,
+        km::VK_F6 => Key::F6,
+        km::VK_F7 => Key::F7,
+        km::VK_F8 => Key::F8,
+        km::VK_F9 => Key::F9,
+        km::VK_F10 => Key::F10,
+        km::VK_F11 => Key::F11,
+        km::VK_F12 => Key::F12,
+        km::VK_F13 => Key::F13,
+        km::VK_F14 => Key::F14,
+        km::VK_F15 => Key::F15,
+        km::VK_F16 => Key::F16,
+        km::VK_F17 => Key::F17,
+        km::VK_F18 => Key::F18,
+        km::VK_F19 => Key::F19,
+        km::VK_F20 => Key::F20,
+        km::VK_F21 => Key::F21,
+        km::VK_F22 => Key::F22,
+        km::VK_F23 => Key::F23,
+        km::VK_F24 => Key::F24,
+        km::VK_NUMLOCK => Key::NumLock,
+        km::VK_SCROLL => Key::ScrollLock,
+        km::VK_LSHIFT => Key::LShift,
+        km::VK_RSHIFT => Key::RShift,
+        km::VK_LCONTROL => Key::LControl,
+        km::VK_RCONTROL => Key::RControl,
+        km::VK_LMENU => Key::LAlt,
+        km::VK_RMENU => Key::RAlt,
+        km::VK_BROWSER_BACK => Key::BrowserBack,
+        km::VK_BROWSER_FORWARD => Key::BrowserForward,
+        km::VK_BROWSER_REFRESH => Key::BrowserRefresh,
+        km::VK_BROWSER_STOP => Key::BrowserStop,
+        km::VK_BROWSER_SEARCH => Key::BrowserSearch,
+        km::VK_BROWSER_FAVORITES => Key::BrowserFavorites,
+        km::VK_BROWSER_HOME => Key::BrowserHome,
+        km::VK_VOLUME_MUTE => Key::VolumeMute,
+        km::VK_VOLUME_DOWN => Key::VolumeDown,
+        km::VK_VOLUME_UP => Key::VolumeUp,
+        km::VK_MEDIA_NEXT_TRACK => Key::MediaNext,
+        km::VK_MEDIA_PREV_TRACK => Key::MediaPrevious,
+        km::VK_MEDIA_STOP => Key::MediaStop,
+        km::VK_MEDIA_PLAY_PAUSE => Key::MediaPlayPause,
+        km::VK_LAUNCH_MAIL => Key::LaunchMail,
+        km::VK_LAUNCH_MEDIA_SELECT => Key::LaunchMediaSelect,
+        km::VK_LAUNCH_APP1 => Key::LaunchApp1,
+        km::VK_LAUNCH_APP2 => Key::LaunchApp2,
+        km::VK_OEM_1 => Key::Semicolon,
+        km::VK_OEM_PLUS => Key::Equal,
+        km::VK_OEM_COMMA => Key::Comma,
+        km::VK_OEM_MINUS => Key::Minus,
+        km::VK_OEM_PERIOD => Key::Period,
+        km::VK_OEM_2 => Key::Slash,
+        km::VK_OEM_3 => Key::Grave,
+        km::VK_OEM_4 => Key::LeftBracket,
+        km::VK_OEM_5 => Key::Backslash,
+        km::VK_OEM_6 => Key::RightBracket,
+        km::VK_OEM_7 => Key::Apostrophe,
+        km::VK_OEM_102 => Key::NonUsBackslash,
+        _ => Key::Unknown(vk),
+    }
 }
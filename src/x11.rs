@@ -0,0 +1,1297 @@
+use core::{
+    cell::Cell,
+    ffi::{c_long, c_ulong},
+    iter, mem, ptr,
+};
+use std::time::Instant;
+
+#[cfg(feature = "gl")]
+use core::ffi::{c_int, c_void, CStr};
+
+use x11::{
+    keysym as ks,
+    xlib::{
+        self, AllocNone, ButtonPressMask, ButtonReleaseMask, CWColormap, CWEventMask, Display,
+        ExposureMask, False, FocusChangeMask, InputOutput, KeyPressMask, KeyReleaseMask, PMaxSize,
+        PMinSize, PPosition, PointerMotionMask, PropModeReplace, StructureNotifyMask, True,
+        USPosition, XChangeProperty, XCloseDisplay, XCloseIM, XConnectionNumber, XContext,
+        XCreateColormap, XCreateIC, XCreateWindow, XDefaultScreen, XDeleteContext, XDestroyIC,
+        XDestroyWindow, XEvent, XFilterEvent, XFindContext, XFree, XFreeColormap,
+        XGetWindowAttributes, XGetWindowProperty, XIMPreeditNothing, XIMStatusNothing, XInternAtom,
+        XKeyEvent, XLookupKeysym, XLookupString, XMapWindow, XNClientWindow_0, XNFocusWindow_0,
+        XNInputStyle_0, XNextEvent, XOpenDisplay, XOpenIM, XPending, XRootWindow, XSaveContext,
+        XSetWMNormalHints, XSetWMProtocols, XSetWindowAttributes, XSizeHints, XStoreName, XSync,
+        XUniqueContext, XkbSetDetectableAutoRepeat, Xutf8LookupString, XIC, XIM,
+    },
+};
+
+use crate::{
+    thread::{self, emit, Pumped},
+    Backend, Button, ControlFlow, Error, Event, Key, Modifiers, WindowBuilder, WindowId,
+};
+
+#[cfg(feature = "gl")]
+use core::{
+    slice,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+#[cfg(feature = "gl")]
+use x11::{
+    glx::{
+        arb, ext, glXChooseVisual, glXCreateContext, glXDestroyContext, glXGetConfig,
+        glXGetCurrentContext, glXGetFBConfigAttrib, glXGetFBConfigs, glXGetProcAddress,
+        glXMakeCurrent, glXQueryDrawable, glXQueryExtensionsString, glXSwapBuffers, GLXContext,
+        GLXDrawable, GLXFBConfig, GLX_ALPHA_SIZE, GLX_BLUE_SIZE, GLX_DEPTH_SIZE, GLX_DOUBLEBUFFER,
+        GLX_GREEN_SIZE, GLX_RED_SIZE, GLX_RGBA, GLX_SAMPLES, GLX_SAMPLE_BUFFERS, GLX_STENCIL_SIZE,
+        GLX_VISUAL_ID,
+    },
+    xlib::{Bool, VisualID, XErrorEvent, XSetErrorHandler, XVisualInfo},
+};
+
+#[cfg(feature = "gl")]
+use crate::{GlAttributes, GlConfig, GlProfile, PresentHook, PresentHooks, Vsync};
+
+#[cfg(not(feature = "gl"))]
+use x11::xlib::{XDefaultDepth, XDefaultVisual};
+
+thread_local! {
+    // Connection shared by every window on the thread, along with how many windows hold it.
+    static DISPLAY: Cell<(*mut Display, usize)> = const { Cell::new((ptr::null_mut(), 0)) };
+
+    // Key the state of each window is saved under with `XSaveContext`.
+    static CONTEXT: XContext = unsafe { XUniqueContext() };
+}
+
+pub struct Window {
+    pub display: *mut Display,
+    pub window: xlib::Window,
+    pub colormap: xlib::Colormap,
+    im: XIM,
+    state: Box<State>,
+
+    /// The window's own context, unless it was built without one.
+    #[cfg(feature = "gl")]
+    pub context: Option<GlContext>,
+
+    #[cfg(feature = "gl")]
+    pixel_format: GlConfig,
+
+    // Kept for making more contexts like the first one.
+    #[cfg(feature = "gl")]
+    info: *mut XVisualInfo,
+
+    #[cfg(feature = "gl")]
+    gl_attributes: Option<GlAttributes>,
+
+    #[cfg(feature = "gl")]
+    present_hooks: PresentHooks<Self>,
+}
+
+// Reachable from `event_loop` through `XFindContext`.
+struct State {
+    ic: XIC,
+    wm_delete_window: xlib::Atom,
+    wm_state: xlib::Atom,
+    size: Cell<[u32; 2]>,
+    minimized: Cell<bool>,
+    closing: Cell<bool>,
+    destroyed: Cell<bool>,
+    key_repeat: Cell<bool>,
+    pressed: [Cell<bool>; 256],
+}
+
+impl Backend for Window {
+    fn build(builder: &WindowBuilder) -> Result<Self, Error> {
+        let display = connect()?;
+
+        let screen = unsafe { XDefaultScreen(display) };
+        let root = unsafe { XRootWindow(display, screen) };
+
+        #[cfg(feature = "gl")]
+        let info = {
+            let info = choose_visual(display, screen, builder.gl_configs);
+
+            if info.is_null() {
+                disconnect();
+                return Err(error("glXChooseVisual"));
+            }
+
+            info
+        };
+
+        #[cfg(feature = "gl")]
+        let (visual, depth) = unsafe { ((*info).visual, (*info).depth) };
+
+        #[cfg(feature = "gl")]
+        let pixel_format = gl_config(display, info);
+
+        #[cfg(not(feature = "gl"))]
+        let (visual, depth) = unsafe {
+            (
+                XDefaultVisual(display, screen),
+                XDefaultDepth(display, screen),
+            )
+        };
+
+        let colormap = unsafe { XCreateColormap(display, root, visual, AllocNone) };
+
+        let mut attributes = XSetWindowAttributes {
+            colormap,
+            event_mask: KeyPressMask
+                | KeyReleaseMask
+                | ButtonPressMask
+                | ButtonReleaseMask
+                | PointerMotionMask
+                | StructureNotifyMask
+                | FocusChangeMask
+                | ExposureMask,
+            ..unsafe { mem::zeroed() }
+        };
+
+        let [width, height] = builder.inner_size.unwrap_or([640, 480]);
+        let [x, y] = builder.position.unwrap_or([0; 2]);
+
+        let window = unsafe {
+            XCreateWindow(
+                display,
+                root,
+                x,
+                y,
+                width,
+                height,
+                0,
+                depth,
+                InputOutput as _,
+                visual,
+                CWColormap | CWEventMask,
+                &mut attributes,
+            )
+        };
+
+        if window == 0 {
+            #[cfg(feature = "gl")]
+            unsafe {
+                XFree(info as _);
+            }
+
+            unsafe { XFreeColormap(display, colormap) };
+            disconnect();
+            return Err(error("XCreateWindow"));
+        }
+
+        unsafe { XStoreName(display, window, builder.name.as_ptr() as _) };
+
+        // Position and size limits are up to the window manager, which reads them from here.
+        let (min_size, max_size) = match builder.resizable {
+            true => (builder.min_size, builder.max_size),
+            false => (Some([width, height]), Some([width, height])),
+        };
+
+        let mut hints = XSizeHints {
+            flags: builder.position.map_or(0, |_| PPosition | USPosition),
+            x,
+            y,
+            ..unsafe { mem::zeroed() }
+        };
+
+        if let Some([width, height]) = min_size {
+            hints.flags |= PMinSize;
+            hints.min_width = width as _;
+            hints.min_height = height as _;
+        }
+
+        if let Some([width, height]) = max_size {
+            hints.flags |= PMaxSize;
+            hints.max_width = width as _;
+            hints.max_height = height as _;
+        }
+
+        unsafe { XSetWMNormalHints(display, window, &mut hints) };
+
+        // There's no core protocol for this, but every window manager honours the Motif hints.
+        if !builder.decorated {
+            let atom = unsafe { XInternAtom(display, c"_MOTIF_WM_HINTS".as_ptr(), 0) };
+
+            // Flags, functions, decorations, input mode and status, with only decorations set.
+            let hints: [c_ulong; 5] = [1 << 1, 0, 0, 0, 0];
+
+            unsafe {
+                XChangeProperty(
+                    display,
+                    window,
+                    atom,
+                    atom,
+                    32,
+                    PropModeReplace,
+                    hints.as_ptr() as _,
+                    hints.len() as _,
+                )
+            };
+        }
+
+        let mut wm_delete_window = unsafe { XInternAtom(display, c"WM_DELETE_WINDOW".as_ptr(), 0) };
+
+        unsafe { XSetWMProtocols(display, window, &mut wm_delete_window, 1) };
+
+        if builder.visible {
+            unsafe { XMapWindow(display, window) };
+        }
+
+        // Auto-repeat otherwise comes as release/press pairs, indistinguishable from real ones.
+        unsafe { XkbSetDetectableAutoRepeat(display, True, ptr::null_mut()) };
+
+        // Without an input method text falls back to `XLookupString`, which only knows Latin-1.
+        let im = unsafe { XOpenIM(display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut()) };
+
+        let ic = match im.is_null() {
+            true => ptr::null_mut(),
+            false => unsafe {
+                XCreateIC(
+                    im,
+                    XNInputStyle_0.as_ptr(),
+                    XIMPreeditNothing | XIMStatusNothing,
+                    XNClientWindow_0.as_ptr(),
+                    window,
+                    XNFocusWindow_0.as_ptr(),
+                    window,
+                    ptr::null_mut::<()>(),
+                )
+            },
+        };
+
+        let state = Box::new(State {
+            ic,
+            wm_delete_window,
+            wm_state: unsafe { XInternAtom(display, c"WM_STATE".as_ptr(), 0) },
+            size: Cell::new([width, height]),
+            minimized: Cell::new(false),
+            closing: Cell::new(false),
+            destroyed: Cell::new(false),
+            key_repeat: Cell::new(true),
+            pressed: [const { Cell::new(false) }; 256],
+        });
+
+        let key = CONTEXT.with(|&key| key);
+        unsafe { XSaveContext(display, window, key, &*state as *const State as _) };
+        thread::opened();
+
+        #[cfg_attr(not(feature = "gl"), allow(unused_mut))]
+        let mut window = Self {
+            display,
+            window,
+            colormap,
+            im,
+            state,
+
+            #[cfg(feature = "gl")]
+            context: None,
+
+            #[cfg(feature = "gl")]
+            pixel_format,
+
+            #[cfg(feature = "gl")]
+            info,
+
+            #[cfg(feature = "gl")]
+            gl_attributes: builder.gl_attributes,
+
+            #[cfg(feature = "gl")]
+            present_hooks: PresentHooks::new(),
+        };
+
+        #[cfg(feature = "gl")]
+        if builder.gl_context {
+            let context = GlContext::new(&window, None)?;
+
+            if !context.make_current(&window) {
+                return Err(error("glXMakeCurrent"));
+            }
+
+            window.context = Some(context);
+        }
+
+        Ok(window)
+    }
+
+    fn id(&self) -> WindowId {
+        WindowId(self.window as _)
+    }
+
+    fn inner_size(&self) -> [u32; 2] {
+        if self.state.closing.get() {
+            return [0, 0];
+        }
+
+        let mut attributes = unsafe { mem::zeroed() };
+
+        let success = unsafe { XGetWindowAttributes(self.display, self.window, &mut attributes) };
+        assert!(success != 0);
+
+        [attributes.width as _, attributes.height as _]
+    }
+
+    fn event_loop(&self, cb: impl FnMut(WindowId, Event)) {
+        let pump = || match unsafe { XPending(self.display) } {
+            0 => Pumped::Nothing,
+            _ => {
+                next(self.display);
+                Pumped::Handled
+            }
+        };
+
+        thread::run(self.id(), cb, pump, |flow| wait(self.display, flow));
+    }
+
+    fn poll_events(&self) -> impl Iterator<Item = Event> + '_ {
+        while unsafe { XPending(self.display) } != 0 {
+            next(self.display);
+        }
+
+        iter::from_fn(|| thread::take(self.id()))
+    }
+
+    fn wait_events(&self) -> impl Iterator<Item = Event> + '_ {
+        thread::wait_for(
+            self.id(),
+            || self.state.destroyed.get(),
+            || {
+                wait(self.display, ControlFlow::Wait);
+                next(self.display);
+            },
+        );
+
+        self.poll_events()
+    }
+
+    // Synced so DestroyNotify is queued before the loop next looks for events, rather than it
+    // reporting `Idle` for a window that's gone.
+    fn close(&self) {
+        if self.state.closing.replace(true) {
+            return;
+        }
+
+        unsafe { XDestroyWindow(self.display, self.window) };
+        unsafe { XSync(self.display, False) };
+    }
+
+    fn set_control_flow(&self, flow: ControlFlow) {
+        thread::set_control_flow(flow);
+    }
+
+    fn set_key_repeat(&self, enabled: bool) {
+        self.state.key_repeat.set(enabled);
+    }
+
+    #[cfg(feature = "gl")]
+    fn swap_buffers(&self) {
+        self.present_hooks.present(self, || {
+            unsafe { glXSwapBuffers(self.display, self.window) };
+        });
+    }
+
+    #[cfg(feature = "gl")]
+    fn set_before_present(&self, hook: Option<PresentHook<Self>>) {
+        self.present_hooks.set_before(hook);
+    }
+
+    #[cfg(feature = "gl")]
+    fn set_after_present(&self, hook: Option<PresentHook<Self>>) {
+        self.present_hooks.set_after(hook);
+    }
+
+    // Set on the window itself, whichever context is current.
+    #[cfg(feature = "gl")]
+    fn set_vsync(&self, vsync: Vsync) -> bool {
+        type SwapInterval = unsafe extern "C" fn(*mut Display, GLXDrawable, c_int);
+
+        let (interval, extension) = match vsync {
+            Vsync::Off => (0, &b"GLX_EXT_swap_control"[..]),
+            Vsync::On => (1, &b"GLX_EXT_swap_control"[..]),
+            Vsync::Adaptive => (-1, &b"GLX_EXT_swap_control_tear"[..]),
+        };
+
+        let screen = unsafe { XDefaultScreen(self.display) };
+
+        if !has_extension(self.display, screen, extension) {
+            return false;
+        }
+
+        let Some(set) = (unsafe { glXGetProcAddress(c"glXSwapIntervalEXT".as_ptr() as _) }) else {
+            return false;
+        };
+
+        let set = unsafe { mem::transmute::<unsafe extern "C" fn(), SwapInterval>(set) };
+        unsafe { set(self.display, self.window, interval) };
+
+        // Drivers may clamp the interval rather than fail, so only what reads back counts.
+        self.vsync() == Some(vsync)
+    }
+
+    #[cfg(feature = "gl")]
+    fn vsync(&self) -> Option<Vsync> {
+        let screen = unsafe { XDefaultScreen(self.display) };
+
+        if !has_extension(self.display, screen, b"GLX_EXT_swap_control") {
+            return None;
+        }
+
+        let query = |attribute| {
+            let mut value = 0;
+            unsafe { glXQueryDrawable(self.display, self.window, attribute, &mut value) };
+            value
+        };
+
+        // A negative interval is stored as its magnitude, with late swaps set to tear.
+        let tear = has_extension(self.display, screen, b"GLX_EXT_swap_control_tear")
+            && query(GLX_LATE_SWAPS_TEAR_EXT) != 0;
+
+        Some(match (query(ext::GLX_SWAP_INTERVAL_EXT), tear) {
+            (0, _) => Vsync::Off,
+            (_, false) => Vsync::On,
+            (_, true) => Vsync::Adaptive,
+        })
+    }
+
+    #[cfg(feature = "gl")]
+    fn pixel_format(&self) -> GlConfig {
+        self.pixel_format
+    }
+
+    #[cfg(feature = "gl")]
+    fn proc_address(&self, name: &CStr) -> *const c_void {
+        let address = unsafe { glXGetProcAddress(name.as_ptr() as _) };
+        address.map_or(ptr::null(), |proc| proc as _)
+    }
+}
+
+impl State {
+    fn text(&self, event: &mut XKeyEvent) -> String {
+        let mut buffer = [0u8; 32];
+        let capacity = buffer.len() as _;
+
+        if self.ic.is_null() {
+            let len = unsafe {
+                XLookupString(
+                    event,
+                    buffer.as_mut_ptr() as _,
+                    capacity,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                )
+            };
+
+            return buffer[..len as usize].iter().map(|&b| b as char).collect();
+        }
+
+        let mut status = 0;
+
+        let len = unsafe {
+            Xutf8LookupString(
+                self.ic,
+                event,
+                buffer.as_mut_ptr() as _,
+                capacity,
+                ptr::null_mut(),
+                &mut status,
+            )
+        };
+
+        match status {
+            xlib::XLookupChars | xlib::XLookupBoth => {
+                String::from_utf8_lossy(&buffer[..len as usize]).into()
+            }
+            _ => String::new(),
+        }
+    }
+}
+
+impl Drop for Window {
+    fn drop(&mut self) {
+        // Before the window it's current on goes away.
+        #[cfg(feature = "gl")]
+        drop(self.context.take());
+
+        unsafe {
+            if !self.state.ic.is_null() {
+                XDestroyIC(self.state.ic);
+            }
+
+            if !self.im.is_null() {
+                XCloseIM(self.im);
+            }
+        }
+
+        // A DestroyNotify still on its way must not find the state freed below.
+        unsafe { XDeleteContext(self.display, self.window, CONTEXT.with(|&key| key)) };
+
+        // Not closed by the application, so nobody hears about it.
+        if !self.state.closing.get() {
+            unsafe { XDestroyWindow(self.display, self.window) };
+        }
+
+        // Counted down by DestroyNotify, unless the window goes before that's been seen.
+        if !self.state.destroyed.get() {
+            thread::destroyed();
+        }
+
+        thread::forget(self.id());
+
+        #[cfg(feature = "gl")]
+        unsafe {
+            XFree(self.info as _);
+        }
+
+        unsafe { XFreeColormap(self.display, self.colormap) };
+        disconnect();
+    }
+}
+
+/// A GL context for windows of the same visual, living apart from any of them.
+#[cfg(feature = "gl")]
+pub struct GlContext {
+    pub display: *mut Display,
+    pub context: GLXContext,
+}
+
+#[cfg(feature = "gl")]
+impl GlContext {
+    /// Made the way the window's own context is, sharing textures, buffers and display lists with
+    /// `share` if given.
+    pub fn new(window: &Window, share: Option<&GlContext>) -> Result<Self, Error> {
+        let share = share.map_or(ptr::null_mut(), |share| share.context);
+        let context = create_context(window.display, window.info, window.gl_attributes, share)?;
+
+        // Holds on to the connection, which may well outlive the window.
+        let display = connect()?;
+
+        Ok(Self { display, context })
+    }
+
+    /// Any window with the visual the context was made for will do.
+    pub fn make_current(&self, window: &Window) -> bool {
+        unsafe { glXMakeCurrent(self.display, window.window, self.context) != 0 }
+    }
+
+    pub fn make_not_current(&self) {
+        if unsafe { glXGetCurrentContext() } == self.context {
+            unsafe { glXMakeCurrent(self.display, 0, ptr::null_mut()) };
+        }
+    }
+}
+
+#[cfg(feature = "gl")]
+impl Drop for GlContext {
+    fn drop(&mut self) {
+        self.make_not_current();
+        unsafe { glXDestroyContext(self.display, self.context) };
+        disconnect();
+    }
+}
+
+fn connect() -> Result<*mut Display, Error> {
+    let (mut display, users) = DISPLAY.get();
+
+    if users == 0 {
+        display = unsafe { XOpenDisplay(ptr::null()) };
+
+        if display.is_null() {
+            return Err(error("XOpenDisplay"));
+        }
+    }
+
+    DISPLAY.set((display, users + 1));
+    Ok(display)
+}
+
+fn disconnect() {
+    let (display, users) = DISPLAY.get();
+
+    if users == 1 {
+        unsafe { XCloseDisplay(display) };
+    }
+
+    DISPLAY.set((display, users - 1));
+}
+
+fn find<'a>(display: *mut Display, window: xlib::Window) -> Option<&'a State> {
+    let key = CONTEXT.with(|&key| key);
+    let mut state = ptr::null_mut();
+
+    match unsafe { XFindContext(display, window, key, &mut state) } {
+        0 => unsafe { (state as *const State).as_ref() },
+        _ => None,
+    }
+}
+
+// Takes the first configuration GLX provides, which only ever hands out visuals meeting every
+// minimum asked for. Failing all of them, the first is retried with its minimums relaxed.
+#[cfg(feature = "gl")]
+fn choose_visual(display: *mut Display, screen: c_int, configs: &[GlConfig]) -> *mut XVisualInfo {
+    let first = configs.first().copied().unwrap_or_default();
+
+    for config in configs.iter().chain(&relaxed(first)) {
+        // Split evenly, 5 bits a channel also gets the usual 5-6-5 visuals.
+        let channel = (config.color_bits / 3) as c_int;
+
+        #[rustfmt::skip]
+        let mut attributes = vec![
+            GLX_RGBA,
+            GLX_RED_SIZE, channel,
+            GLX_GREEN_SIZE, channel,
+            GLX_BLUE_SIZE, channel,
+            GLX_ALPHA_SIZE, config.alpha_bits as _,
+            GLX_DEPTH_SIZE, config.depth_bits as _,
+            GLX_STENCIL_SIZE, config.stencil_bits as _,
+        ];
+
+        // Only single-buffered visuals are considered without it.
+        if config.double_buffer {
+            attributes.push(GLX_DOUBLEBUFFER);
+        }
+
+        if config.samples > 0 {
+            attributes.extend([GLX_SAMPLE_BUFFERS, 1, GLX_SAMPLES, config.samples as _]);
+        }
+
+        if config.srgb {
+            attributes.extend([GLX_FRAMEBUFFER_SRGB_CAPABLE_ARB, True]);
+        }
+
+        attributes.push(0);
+
+        let info = unsafe { glXChooseVisual(display, screen, attributes.as_mut_ptr()) };
+
+        if !info.is_null() {
+            return info;
+        }
+    }
+
+    ptr::null_mut()
+}
+
+// Asks for at least a bit of whatever the config wants, which GLX answers with the deepest buffer it
+// has, then the same with the other kind of buffering.
+#[cfg(feature = "gl")]
+fn relaxed(config: GlConfig) -> [GlConfig; 2] {
+    let some = |bits, least| match bits {
+        0 => 0,
+        _ => least,
+    };
+
+    let config = GlConfig {
+        color_bits: some(config.color_bits, 3),
+        alpha_bits: some(config.alpha_bits, 1),
+        depth_bits: some(config.depth_bits, 1),
+        stencil_bits: some(config.stencil_bits, 1),
+        samples: 0,
+        srgb: false,
+        ..config
+    };
+
+    [
+        config,
+        GlConfig {
+            double_buffer: !config.double_buffer,
+            ..config
+        },
+    ]
+}
+
+#[cfg(feature = "gl")]
+fn gl_config(display: *mut Display, info: *mut XVisualInfo) -> GlConfig {
+    // Attributes from extensions the server lacks are left at 0.
+    let get = |attribute| {
+        let mut value = 0;
+        unsafe { glXGetConfig(display, info, attribute, &mut value) };
+        value
+    };
+
+    GlConfig {
+        color_bits: (get(GLX_RED_SIZE) + get(GLX_GREEN_SIZE) + get(GLX_BLUE_SIZE)) as _,
+        alpha_bits: get(GLX_ALPHA_SIZE) as _,
+        depth_bits: get(GLX_DEPTH_SIZE) as _,
+        stencil_bits: get(GLX_STENCIL_SIZE) as _,
+        samples: get(GLX_SAMPLES) as _,
+        double_buffer: get(GLX_DOUBLEBUFFER) != 0,
+        srgb: get(GLX_FRAMEBUFFER_SRGB_CAPABLE_ARB) != 0,
+    }
+}
+
+// Goes through GLX_ARB_create_context when asked to and the server has it, and makes a legacy
+// context otherwise.
+#[cfg(feature = "gl")]
+fn create_context(
+    display: *mut Display,
+    info: *mut XVisualInfo,
+    attributes: Option<GlAttributes>,
+    share: GLXContext,
+) -> Result<GLXContext, Error> {
+    type CreateContextAttribs = unsafe extern "C" fn(
+        *mut Display,
+        GLXFBConfig,
+        GLXContext,
+        Bool,
+        *const c_int,
+    ) -> GLXContext;
+
+    static FAILED: AtomicBool = AtomicBool::new(false);
+
+    unsafe extern "C" fn failed(_: *mut Display, _: *mut XErrorEvent) -> c_int {
+        FAILED.store(true, Ordering::Relaxed);
+        0
+    }
+
+    let screen = unsafe { (*info).screen };
+
+    let modern = attributes.and_then(|attributes| {
+        if !has_extension(display, screen, b"GLX_ARB_create_context") {
+            return None;
+        }
+
+        let name = c"glXCreateContextAttribsARB".as_ptr() as _;
+        let create = unsafe { glXGetProcAddress(name) }?;
+        let create =
+            unsafe { mem::transmute::<unsafe extern "C" fn(), CreateContextAttribs>(create) };
+
+        Some((attributes, create, fb_config(display, screen, info)?))
+    });
+
+    let Some((attributes, create, config)) = modern else {
+        let context = unsafe { glXCreateContext(display, info, share, True) };
+
+        return match context.is_null() {
+            true => Err(error("glXCreateContext")),
+            false => Ok(context),
+        };
+    };
+
+    let [major, minor] = attributes.version;
+
+    let profile = match attributes.profile {
+        GlProfile::Core => arb::GLX_CONTEXT_CORE_PROFILE_BIT_ARB,
+        GlProfile::Compatibility => arb::GLX_CONTEXT_COMPATIBILITY_PROFILE_BIT_ARB,
+    };
+
+    let flags = match attributes.debug {
+        true => arb::GLX_CONTEXT_DEBUG_BIT_ARB,
+        false => 0,
+    };
+
+    #[rustfmt::skip]
+    let attributes = [
+        arb::GLX_CONTEXT_MAJOR_VERSION_ARB, major as _,
+        arb::GLX_CONTEXT_MINOR_VERSION_ARB, minor as _,
+        arb::GLX_CONTEXT_PROFILE_MASK_ARB, profile,
+        arb::GLX_CONTEXT_FLAGS_ARB, flags,
+        0,
+    ];
+
+    // Turning the attributes down raises an X error, which would end the process by default.
+    FAILED.store(false, Ordering::Relaxed);
+    let previous = unsafe { XSetErrorHandler(Some(failed)) };
+
+    let context = unsafe { create(display, config, share, True, attributes.as_ptr()) };
+
+    unsafe { XSync(display, False) };
+    unsafe { XSetErrorHandler(previous) };
+
+    match context.is_null() || FAILED.load(Ordering::Relaxed) {
+        false => Ok(context),
+
+        true => {
+            if !context.is_null() {
+                unsafe { glXDestroyContext(display, context) };
+            }
+
+            Err(error("glXCreateContextAttribsARB"))
+        }
+    }
+}
+
+// Any name resolves through `glXGetProcAddress`, whether the server supports it or not, so
+// extensions are looked up here first.
+#[cfg(feature = "gl")]
+fn has_extension(display: *mut Display, screen: c_int, name: &[u8]) -> bool {
+    let extensions = unsafe { glXQueryExtensionsString(display, screen) };
+
+    !extensions.is_null()
+        && unsafe { CStr::from_ptr(extensions) }
+            .to_bytes()
+            .split(|&byte| byte == b' ')
+            .any(|extension| extension == name)
+}
+
+// The FBConfig behind a visual, which is what GLX_ARB_create_context takes.
+#[cfg(feature = "gl")]
+fn fb_config(display: *mut Display, screen: c_int, info: *mut XVisualInfo) -> Option<GLXFBConfig> {
+    let mut count = 0;
+    let configs = unsafe { glXGetFBConfigs(display, screen, &mut count) };
+
+    if configs.is_null() {
+        return None;
+    }
+
+    let visual = unsafe { (*info).visualid };
+
+    let found = unsafe { slice::from_raw_parts(configs, count as _) }
+        .iter()
+        .copied()
+        .find(|&config| {
+            let mut id = 0;
+            unsafe { glXGetFBConfigAttrib(display, config, GLX_VISUAL_ID, &mut id) };
+            id as VisualID == visual
+        });
+
+    unsafe { XFree(configs as _) };
+    found
+}
+
+// From the GLX_ARB_framebuffer_sRGB and GLX_EXT_swap_control_tear specifications.
+#[cfg(feature = "gl")]
+const GLX_FRAMEBUFFER_SRGB_CAPABLE_ARB: c_int = 0x20B2;
+
+#[cfg(feature = "gl")]
+const GLX_LATE_SWAPS_TEAR_EXT: c_int = 0x20F3;
+
+// Translates the next event from the connection, queueing what it reports.
+fn next(display: *mut Display) {
+    let mut event = unsafe { mem::zeroed::<XEvent>() };
+    unsafe { XNextEvent(display, &mut event) };
+
+    // Input methods swallow dead keys and compose sequences.
+    if unsafe { XFilterEvent(&mut event, 0) } != 0 {
+        return;
+    }
+
+    let window = unsafe { event.any.window };
+    let id = WindowId(window as _);
+
+    // Stragglers for windows already dropped.
+    let Some(state) = find(display, window) else {
+        return;
+    };
+
+    match event.get_type() {
+        xlib::KeyPress => {
+            let keycode = unsafe { event.key.keycode };
+            let repeat = state.pressed[keycode as u8 as usize].replace(true);
+            let (key, physical, scancode, extended) = key(unsafe { &mut event.key });
+
+            if !repeat || state.key_repeat.get() {
+                let press = Event::Press {
+                    key,
+                    physical,
+                    scancode,
+                    extended,
+                    repeat,
+                    modifiers: key_modifiers(unsafe { event.key.state }, key, true),
+                };
+
+                emit(id, press);
+            }
+
+            for c in state.text(unsafe { &mut event.key }).chars() {
+                emit(id, Event::Text(c));
+            }
+        }
+
+        xlib::KeyRelease => {
+            let keycode = unsafe { event.key.keycode };
+            state.pressed[keycode as u8 as usize].set(false);
+
+            let (key, physical, scancode, extended) = key(unsafe { &mut event.key });
+
+            let release = Event::Release {
+                key,
+                physical,
+                scancode,
+                extended,
+                modifiers: key_modifiers(unsafe { event.key.state }, key, false),
+            };
+
+            emit(id, release);
+        }
+
+        xlib::MotionNotify => {
+            let motion = unsafe { event.motion };
+
+            let motion = Event::Motion {
+                position: [motion.x, motion.y],
+                modifiers: modifiers(motion.state),
+            };
+
+            emit(id, motion);
+        }
+
+        // The core protocol reports wheel rotation as presses of buttons 4 and 5.
+        xlib::ButtonPress => {
+            let button = unsafe { event.button };
+            let modifiers = modifiers(button.state);
+
+            match button.button {
+                4 => emit(
+                    id,
+                    Event::Wheel {
+                        delta: 1.,
+                        modifiers,
+                    },
+                ),
+                5 => emit(
+                    id,
+                    Event::Wheel {
+                        delta: -1.,
+                        modifiers,
+                    },
+                ),
+
+                button => {
+                    if let Some(button) = mouse_button(button) {
+                        emit(id, Event::ButtonPress { button, modifiers });
+                    }
+                }
+            }
+        }
+
+        xlib::ButtonRelease => {
+            let button = unsafe { event.button };
+            let modifiers = modifiers(button.state);
+
+            if let Some(button) = mouse_button(button.button) {
+                emit(id, Event::ButtonRelease { button, modifiers });
+            }
+        }
+
+        xlib::ClientMessage => {
+            let data = unsafe { event.client_message.data.get_long(0) };
+
+            // Closing is up to the application, which may call `close` in response.
+            if data as xlib::Atom == state.wm_delete_window {
+                emit(id, Event::CloseRequested);
+            }
+        }
+
+        xlib::ConfigureNotify => {
+            let configure = unsafe { event.configure };
+            let size = [configure.width as _, configure.height as _];
+
+            if state.size.replace(size) != size {
+                emit(id, Event::Resize(size));
+            }
+        }
+
+        xlib::FocusIn => emit(id, Event::Focus(true)),
+        // Releases that happen while unfocused never arrive.
+        xlib::FocusOut => {
+            state.pressed.iter().for_each(|pressed| pressed.set(false));
+            emit(id, Event::Focus(false));
+        }
+
+        // Window managers unmap iconified windows, but hiding or closing one unmaps it too.
+        xlib::UnmapNotify if !state.closing.get() && iconic(display, window, state.wm_state) => {
+            state.minimized.set(true);
+            emit(id, Event::Minimized);
+        }
+
+        xlib::MapNotify if state.minimized.replace(false) => emit(id, Event::Restored),
+
+        xlib::DestroyNotify => {
+            unsafe { XDeleteContext(display, window, CONTEXT.with(|&key| key)) };
+
+            state.destroyed.set(true);
+            thread::destroyed();
+
+            emit(id, Event::Destroyed);
+        }
+
+        _ => {}
+    }
+}
+
+// Iconified windows have their WM_STATE set to IconicState by the window manager, per the ICCCM.
+fn iconic(display: *mut Display, window: xlib::Window, wm_state: xlib::Atom) -> bool {
+    const ICONIC_STATE: c_long = 3;
+
+    let mut kind = 0;
+    let mut format = 0;
+    let mut count = 0;
+    let mut remaining = 0;
+    let mut data = ptr::null_mut();
+
+    let status = unsafe {
+        XGetWindowProperty(
+            display,
+            window,
+            wm_state,
+            0,
+            1,
+            False,
+            wm_state,
+            &mut kind,
+            &mut format,
+            &mut count,
+            &mut remaining,
+            &mut data,
+        )
+    };
+
+    if status != 0 || data.is_null() {
+        return false;
+    }
+
+    let state = (format == 32 && count == 1).then(|| unsafe { *(data as *const c_long) });
+    unsafe { XFree(data as _) };
+
+    state == Some(ICONIC_STATE)
+}
+
+// Sleeps until the connection has something to read, or `Poll` or the deadline say otherwise.
+fn wait(display: *mut Display, flow: ControlFlow) {
+    let timeout = match flow {
+        ControlFlow::Poll | ControlFlow::Exit => return,
+        ControlFlow::Wait => -1,
+
+        // Rounded up, waking before the deadline would only mean waiting again.
+        ControlFlow::WaitUntil(deadline) => {
+            let left = deadline.saturating_duration_since(Instant::now());
+            left.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32
+        }
+    };
+
+    // Flushes requests the callback made, and catches events their replies already brought in.
+    if unsafe { XPending(display) } != 0 {
+        return;
+    }
+
+    let mut fd = libc::pollfd {
+        fd: unsafe { XConnectionNumber(display) },
+        events: libc::POLLIN,
+        revents: 0,
+    };
+
+    unsafe { libc::poll(&mut fd, 1, timeout) };
+}
+
+// Assumes the usual modifier mapping: Alt on Mod1, Num Lock on Mod2 and Super on Mod4.
+fn modifiers(state: u32) -> Modifiers {
+    let mut modifiers = Modifiers::empty();
+
+    modifiers.set(Modifiers::SHIFT, state & xlib::ShiftMask != 0);
+    modifiers.set(Modifiers::CTRL, state & xlib::ControlMask != 0);
+    modifiers.set(Modifiers::ALT, state & xlib::Mod1Mask != 0);
+    modifiers.set(Modifiers::LOGO, state & xlib::Mod4Mask != 0);
+    modifiers.set(Modifiers::CAPS_LOCK, state & xlib::LockMask != 0);
+    modifiers.set(Modifiers::NUM_LOCK, state & xlib::Mod2Mask != 0);
+    modifiers
+}
+
+// Key events carry the state from before the key, unlike Windows which includes it.
+fn key_modifiers(state: u32, key: Key, down: bool) -> Modifiers {
+    let mut modifiers = modifiers(state);
+
+    match key {
+        Key::LShift | Key::RShift => modifiers.set(Modifiers::SHIFT, down),
+        Key::LControl | Key::RControl => modifiers.set(Modifiers::CTRL, down),
+        Key::LAlt | Key::RAlt => modifiers.set(Modifiers::ALT, down),
+        Key::LSuper | Key::RSuper => modifiers.set(Modifiers::LOGO, down),
+        _ => {}
+    }
+
+    modifiers
+}
+
+// Xlib reports failures through the error handler, so there's no code to carry.
+fn error(function: &'static str) -> Error {
+    Error { function, code: 0 }
+}
+
+fn mouse_button(button: u32) -> Option<Button> {
+    match button {
+        xlib::Button1 => Some(Button::Left),
+        xlib::Button2 => Some(Button::Middle),
+        xlib::Button3 => Some(Button::Right),
+        8 => Some(Button::X1),
+        9 => Some(Button::X2),
+        _ => None,
+    }
+}
+
+// Returns the logical key, physical key, scancode and extended flag of a keystroke.
+fn key(event: &mut XKeyEvent) -> (Key, Key, u32, bool) {
+    let (scancode, extended) = scancode(event.keycode);
+    let physical = Key::from_scancode(scancode, extended);
+
+    (logical_key(event), physical, scancode, extended)
+}
+
+// X keycodes are evdev codes offset by 8. Evdev codes match scan code set 1 for the main block, the
+// rest need translating into the Windows convention `Key::from_scancode` expects.
+fn scancode(keycode: u32) -> (u32, bool) {
+    match keycode.wrapping_sub(8) {
+        69 => (0x45, true),
+        code @ 0..=88 => (code, false),
+        92 => (0x79, false),
+        93 => (0x70, false),
+        94 => (0x7B, false),
+        96 => (0x1C, true),
+        97 => (0x1D, true),
+        98 => (0x35, true),
+        99 => (0x37, true),
+        100 => (0x38, true),
+        102 => (0x47, true),
+        103 => (0x48, true),
+        104 => (0x49, true),
+        105 => (0x4B, true),
+        106 => (0x4D, true),
+        107 => (0x4F, true),
+        108 => (0x50, true),
+        109 => (0x51, true),
+        110 => (0x52, true),
+        111 => (0x53, true),
+        113 => (0x20, true),
+        114 => (0x2E, true),
+        115 => (0x30, true),
+        119 => (0x45, false),
+        125 => (0x5B, true),
+        126 => (0x5C, true),
+        127 => (0x5D, true),
+        128 => (0x68, true),
+        142 => (0x5F, true),
+        155 => (0x6C, true),
+        156 => (0x66, true),
+        158 => (0x6A, true),
+        159 => (0x69, true),
+        163 => (0x19, true),
+        164 => (0x22, true),
+        165 => (0x10, true),
+        166 => (0x24, true),
+        172 => (0x32, true),
+        173 => (0x67, true),
+        code @ 183..=193 => (0x64 + code - 183, false),
+        194 => (0x76, false),
+        217 => (0x65, true),
+        226 => (0x6D, true),
+        code => (code, false),
+    }
+}
+
+fn logical_key(event: &mut XKeyEvent) -> Key {
+    let keysym = unsafe { XLookupKeysym(event, 0) } as u32;
+
+    match keysym {
+        ks::XK_a..=ks::XK_z => LETTERS[(keysym - ks::XK_a) as usize],
+        ks::XK_0..=ks::XK_9 => DIGITS[(keysym - ks::XK_0) as usize],
+        ks::XK_KP_0..=ks::XK_KP_9 => NUMPAD[(keysym - ks::XK_KP_0) as usize],
+        ks::XK_F1..=ks::XK_F24 => FUNCTION[(keysym - ks::XK_F1) as usize],
+        ks::XK_BackSpace => Key::Backspace,
+        ks::XK_Tab | ks::XK_ISO_Left_Tab => Key::Tab,
+        ks::XK_Clear => Key::Clear,
+        ks::XK_Return => Key::Return,
+        ks::XK_KP_Enter => Key::NumpadEnter,
+        ks::XK_Shift_L => Key::LShift,
+        ks::XK_Shift_R => Key::RShift,
+        ks::XK_Control_L => Key::LControl,
+        ks::XK_Control_R => Key::RControl,
+        ks::XK_Alt_L => Key::LAlt,
+        ks::XK_Alt_R | ks::XK_ISO_Level3_Shift => Key::RAlt,
+        ks::XK_Pause => Key::Pause,
+        ks::XK_Caps_Lock => Key::CapsLock,
+        ks::XK_Escape => Key::Escape,
+        ks::XK_space => Key::Space,
+        ks::XK_Prior | ks::XK_KP_Prior => Key::PageUp,
+        ks::XK_Next | ks::XK_KP_Next => Key::PageDown,
+        ks::XK_End | ks::XK_KP_End => Key::End,
+        ks::XK_Home | ks::XK_KP_Home => Key::Home,
+        ks::XK_Left | ks::XK_KP_Left => Key::Left,
+        ks::XK_Up | ks::XK_KP_Up => Key::Up,
+        ks::XK_Right | ks::XK_KP_Right => Key::Right,
+        ks::XK_Down | ks::XK_KP_Down => Key::Down,
+        ks::XK_Select => Key::Select,
+        ks::XK_Print => Key::PrintScreen,
+        ks::XK_Execute => Key::Execute,
+        ks::XK_Insert | ks::XK_KP_Insert => Key::Insert,
+        ks::XK_Delete | ks::XK_KP_Delete => Key::Delete,
+        ks::XK_Help => Key::Help,
+        ks::XK_Super_L => Key::LSuper,
+        ks::XK_Super_R => Key::RSuper,
+        ks::XK_Menu => Key::Menu,
+        ks::XK_KP_Multiply => Key::NumpadMultiply,
+        ks::XK_KP_Add => Key::NumpadAdd,
+        ks::XK_KP_Separator => Key::NumpadSeparator,
+        ks::XK_KP_Subtract => Key::NumpadSubtract,
+        ks::XK_KP_Decimal => Key::NumpadDecimal,
+        ks::XK_KP_Divide => Key::NumpadDivide,
+        ks::XK_Num_Lock => Key::NumLock,
+        ks::XK_Scroll_Lock => Key::ScrollLock,
+        ks::XK_semicolon => Key::Semicolon,
+        ks::XK_equal => Key::Equal,
+        ks::XK_comma => Key::Comma,
+        ks::XK_minus => Key::Minus,
+        ks::XK_period => Key::Period,
+        ks::XK_slash => Key::Slash,
+        ks::XK_grave => Key::Grave,
+        ks::XK_bracketleft => Key::LeftBracket,
+        ks::XK_backslash => Key::Backslash,
+        ks::XK_bracketright => Key::RightBracket,
+        ks::XK_apostrophe => Key::Apostrophe,
+        ks::XK_less => Key::NonUsBackslash,
+        _ => Key::Unknown(keysym),
+    }
+}
+
+#[rustfmt::skip]
+const LETTERS: [Key; 26] = [
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K,
+    Key::L, Key::M, Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V,
+    Key::W, Key::X, Key::Y, Key::Z,
+];
+
+#[rustfmt::skip]
+const DIGITS: [Key; 10] = [
+    Key::Digit0, Key::Digit1, Key::Digit2, Key::Digit3, Key::Digit4,
+    Key::Digit5, Key::Digit6, Key::Digit7, Key::Digit8, Key::Digit9,
+];
+
+#[rustfmt::skip]
+const NUMPAD: [Key; 10] = [
+    Key::Numpad0, Key::Numpad1, Key::Numpad2, Key::Numpad3, Key::Numpad4,
+    Key::Numpad5, Key::Numpad6, Key::Numpad7, Key::Numpad8, Key::Numpad9,
+];
+
+#[rustfmt::skip]
+const FUNCTION: [Key; 24] = [
+    Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6,
+    Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12,
+    Key::F13, Key::F14, Key::F15, Key::F16, Key::F17, Key::F18,
+    Key::F19, Key::F20, Key::F21, Key::F22, Key::F23, Key::F24,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Keycodes as the evdev driver reports them, evdev codes offset by 8.
+    fn physical(evdev: u32) -> Key {
+        let (scancode, extended) = scancode(evdev + 8);
+        Key::from_scancode(scancode, extended)
+    }
+
+    #[test]
+    fn evdev_codes_map_to_scancodes() {
+        assert_eq!(scancode(30 + 8), (0x1E, false));
+        assert_eq!(scancode(69 + 8), (0x45, true));
+        assert_eq!(scancode(97 + 8), (0x1D, true));
+        assert_eq!(scancode(119 + 8), (0x45, false));
+    }
+
+    #[test]
+    fn physical_keys_ignore_the_layout() {
+        assert_eq!(physical(30), Key::A);
+        assert_eq!(physical(69), Key::NumLock);
+        assert_eq!(physical(97), Key::RControl);
+        assert_eq!(physical(119), Key::Pause);
+
+        // Typing 'a' on AZERTY, 'q' on QWERTY, both from the same key.
+        assert_eq!(physical(16), Key::Q);
+    }
+}
@@ -0,0 +1,219 @@
+use xenocore::{self as xc, headless, Backend};
+
+#[test]
+fn scripted_events_drive_the_loop() {
+    let window = headless::Window::new(c"headless").unwrap();
+
+    window.push(xc::Event::Press {
+        key: xc::Key::Space,
+        physical: xc::Key::Space,
+        scancode: 0x39,
+        extended: false,
+        repeat: false,
+        modifiers: xc::Modifiers::empty(),
+    });
+
+    window.push(xc::Event::Idle);
+
+    window.push(xc::Event::Release {
+        key: xc::Key::Space,
+        physical: xc::Key::Space,
+        scancode: 0x39,
+        extended: false,
+        modifiers: xc::Modifiers::empty(),
+    });
+
+    window.push(xc::Event::Idle);
+
+    let mut pressed = false;
+    let mut frames = 0;
+
+    window.event_loop(|_, event| match event {
+        xc::Event::Press {
+            key: xc::Key::Space,
+            ..
+        } => pressed = true,
+        xc::Event::Release {
+            key: xc::Key::Space,
+            ..
+        } => pressed = false,
+
+        xc::Event::Idle => {
+            let color = if pressed { [255; 4] } else { [0; 4] };
+            window.framebuffer().fill(color);
+            frames += 1;
+        }
+
+        _ => {}
+    });
+
+    assert_eq!(frames, 2);
+    assert_eq!(window.framebuffer()[0], [0; 4]);
+}
+
+#[test]
+fn framebuffer_follows_size() {
+    let window = headless::Window::new(c"headless").unwrap();
+    assert_eq!(window.framebuffer().len(), 640 * 480);
+
+    window.resize([320, 200]);
+    assert_eq!(window.inner_size(), [320, 200]);
+    assert_eq!(window.framebuffer().len(), 320 * 200);
+}
+
+#[test]
+fn builder_sets_inner_size() {
+    let window: headless::Window = xc::WindowBuilder::new(c"headless")
+        .inner_size([320, 200])
+        .resizable(false)
+        .build()
+        .unwrap();
+
+    assert_eq!(window.inner_size(), [320, 200]);
+    assert_eq!(window.framebuffer().len(), 320 * 200);
+}
+
+#[test]
+fn close_reports_destroyed() {
+    let window = headless::Window::new(c"headless").unwrap();
+    let other = headless::Window::new(c"other").unwrap();
+    assert_ne!(window.id(), other.id());
+
+    window.push(xc::Event::CloseRequested);
+    window.push(xc::Event::Idle);
+
+    let mut events = Vec::new();
+
+    window.event_loop(|id, event| {
+        assert_eq!(id, window.id());
+
+        if let xc::Event::CloseRequested = event {
+            window.close();
+        }
+
+        events.push(event);
+    });
+
+    assert!(matches!(
+        events[..],
+        [xc::Event::CloseRequested, xc::Event::Destroyed]
+    ));
+
+    assert_eq!(window.inner_size(), [0, 0]);
+}
+
+#[test]
+fn exit_returns_early() {
+    let window = headless::Window::new(c"headless").unwrap();
+    window.push(xc::Event::Idle);
+    window.push(xc::Event::Resize([320, 200]));
+
+    let mut events = 0;
+
+    window.event_loop(|_, _| {
+        window.set_control_flow(xc::ControlFlow::Exit);
+        events += 1;
+    });
+
+    assert_eq!(events, 1);
+    assert!(!window.is_closed());
+    assert_eq!(window.control_flow(), xc::ControlFlow::Poll);
+
+    // The rest is still there for the next run.
+    window.event_loop(|_, event| assert!(matches!(event, xc::Event::Resize([320, 200]))));
+}
+
+#[test]
+fn poll_events_drains_the_queue() {
+    let window = headless::Window::new(c"headless").unwrap();
+    window.resize([320, 200]);
+    window.push(xc::Event::CloseRequested);
+
+    let mut events = window.poll_events();
+    assert!(matches!(events.next(), Some(xc::Event::Resize([320, 200]))));
+    assert!(matches!(events.next(), Some(xc::Event::CloseRequested)));
+    assert!(events.next().is_none());
+
+    window.close();
+    let events: Vec<_> = window.wait_events().collect();
+    assert!(matches!(events[..], [xc::Event::Destroyed]));
+}
+
+#[cfg(feature = "gl")]
+#[test]
+fn pixel_format_is_the_first_asked_for() {
+    let stencil = xc::GlConfig {
+        stencil_bits: 8,
+        ..xc::GlConfig::DEFAULT
+    };
+
+    let window: headless::Window = xc::WindowBuilder::new(c"headless")
+        .gl_configs(&[stencil, xc::GlConfig::DEFAULT])
+        .build()
+        .unwrap();
+
+    assert_eq!(window.pixel_format(), stencil);
+    assert!(stencil.satisfies(&xc::GlConfig::DEFAULT));
+    assert!(!xc::GlConfig::DEFAULT.satisfies(&stencil));
+}
+
+#[cfg(feature = "gl")]
+#[test]
+fn vsync_reads_back() {
+    let window = headless::Window::new(c"headless").unwrap();
+
+    assert_eq!(window.vsync(), Some(xc::Vsync::On));
+    assert!(window.set_vsync(xc::Vsync::Adaptive));
+    assert_eq!(window.vsync(), Some(xc::Vsync::Adaptive));
+}
+
+#[cfg(feature = "gl")]
+#[test]
+fn present_hooks_wrap_the_swap() {
+    use std::{cell::RefCell, rc::Rc};
+
+    let window = headless::Window::new(c"headless").unwrap();
+    let seen = Rc::new(RefCell::new(Vec::new()));
+
+    let before = seen.clone();
+    window.set_before_present(Some(Box::new(move |window: &headless::Window| {
+        before.borrow_mut().push(("before", window.frames()));
+    })));
+
+    // Removes itself after the first frame.
+    let after = seen.clone();
+    window.set_after_present(Some(Box::new(move |window: &headless::Window| {
+        after.borrow_mut().push(("after", window.frames()));
+        window.set_after_present(None);
+    })));
+
+    window.swap_buffers();
+    window.swap_buffers();
+
+    assert_eq!(*seen.borrow(), [("before", 0), ("after", 1), ("before", 1)]);
+}
+
+#[cfg(feature = "gl")]
+#[test]
+fn present_hooks_can_install_each_other() {
+    use std::{cell::Cell, rc::Rc};
+
+    let window = headless::Window::new(c"headless").unwrap();
+    let (before, after) = (Rc::new(Cell::new(0)), Rc::new(Cell::new(0)));
+
+    let (counted, installed) = (before.clone(), after.clone());
+    window.set_before_present(Some(Box::new(move |window: &headless::Window| {
+        counted.set(counted.get() + 1);
+
+        let installed = installed.clone();
+        window.set_after_present(Some(Box::new(move |_: &headless::Window| {
+            installed.set(installed.get() + 1);
+        })));
+    })));
+
+    window.swap_buffers();
+    window.swap_buffers();
+
+    // Replacing the other slot leaves the running hook in place.
+    assert_eq!((before.get(), after.get()), (2, 2));
+}
@@ -0,0 +1,178 @@
+#![cfg(windows)]
+
+use std::{
+    cell::Cell,
+    time::{Duration, Instant},
+};
+
+use windows_sys::Win32::UI::WindowsAndMessaging::{PostMessageW, WM_CHAR};
+use xenocore::{self as xc, Backend};
+
+#[test]
+fn callback_is_never_reentered() {
+    let window = xc::Window::new(c"win32").unwrap();
+    let depth = Cell::new(0);
+    let mut destroyed = false;
+
+    window.event_loop(|_, event| {
+        assert_eq!(depth.replace(depth.get() + 1), 0);
+
+        match event {
+            // Both send messages to the window procedure before returning.
+            xc::Event::Idle => {
+                window.set_inner_size([320, 200]);
+                window.close();
+            }
+
+            xc::Event::Destroyed => destroyed = true,
+            _ => {}
+        }
+
+        depth.set(depth.get() - 1);
+    });
+
+    assert!(destroyed);
+}
+
+#[test]
+fn text_decodes_surrogate_pairs() {
+    let window = xc::Window::new(c"win32").unwrap();
+
+    // U+1F600 takes a surrogate pair, U+00E9 a single code unit.
+    for unit in [0xD83D, 0xDE00, 0xE9] {
+        unsafe { PostMessageW(window.hwnd, WM_CHAR, unit, 0) };
+    }
+
+    let text: String = window
+        .poll_events()
+        .filter_map(|event| match event {
+            xc::Event::Text(c) => Some(c),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(text, "\u{1F600}\u{E9}");
+}
+
+#[test]
+fn windows_outlive_the_loop() {
+    let window = xc::Window::new(c"win32").unwrap();
+    let other = xc::Window::new(c"other").unwrap();
+
+    window.event_loop(|_, event| {
+        if let xc::Event::Idle = event {
+            window.close();
+            other.close();
+        }
+    });
+
+    // Messages sent while dropping, or while no loop runs, have nowhere dangling to go.
+    let late = xc::Window::new(c"late").unwrap();
+    late.set_inner_size([320, 200]);
+
+    drop(window);
+    drop(late);
+    drop(other);
+}
+
+#[test]
+fn nested_loops_share_the_queue() {
+    let outer = xc::Window::new(c"outer").unwrap();
+    let mut destroyed = Vec::new();
+
+    outer.event_loop(|_, event| {
+        if let xc::Event::Idle = event {
+            let inner = xc::Window::new(c"inner").unwrap();
+
+            // Runs until every window is gone, the outer one included.
+            inner.event_loop(|id, event| match event {
+                xc::Event::Idle => {
+                    inner.close();
+                    outer.close();
+                }
+
+                xc::Event::Destroyed => destroyed.push(id),
+                _ => {}
+            });
+
+            assert!(destroyed.contains(&inner.id()));
+        }
+    });
+
+    assert!(destroyed.contains(&outer.id()));
+}
+
+#[test]
+fn wait_until_sleeps_past_the_deadline() {
+    let window = xc::Window::new(c"win32").unwrap();
+    let mut deadline = None;
+
+    // Any message wakes the loop early, but polling would report thousands of these.
+    let mut early = 0;
+
+    window.event_loop(|_, event| {
+        if let xc::Event::Idle = event {
+            match deadline {
+                None => {
+                    let at = Instant::now() + Duration::from_millis(50);
+                    window.set_control_flow(xc::ControlFlow::WaitUntil(at));
+                    deadline = Some(at);
+                }
+
+                Some(at) if Instant::now() < at => early += 1,
+
+                Some(_) => {
+                    window.set_control_flow(xc::ControlFlow::Poll);
+                    window.close();
+                }
+            }
+        }
+    });
+
+    assert!(early < 25, "woke up {early} times before the deadline");
+}
+
+#[test]
+fn poll_events_leave_other_windows_alone() {
+    let window = xc::Window::new(c"win32").unwrap();
+    let other = xc::Window::new(c"other").unwrap();
+    window.poll_events().for_each(drop);
+
+    other.set_inner_size([320, 200]);
+    let resized = |event| matches!(event, xc::Event::Resize([320, 200]));
+    assert!(!window.poll_events().any(resized));
+    assert!(other.poll_events().any(resized));
+
+    other.close();
+    let destroyed = matches!(other.wait_events().last(), Some(xc::Event::Destroyed));
+    assert!(destroyed);
+}
+
+#[cfg(feature = "gl")]
+#[test]
+fn gl_attributes_for_2_1_give_a_context() {
+    // Whichever path the driver takes, 2.1 is there on all of them.
+    let attributes = xc::GlAttributes {
+        version: [2, 1],
+        profile: xc::GlProfile::Compatibility,
+        debug: false,
+    };
+
+    let window: xc::Window = xc::WindowBuilder::new(c"win32")
+        .gl_attributes(attributes)
+        .build()
+        .unwrap();
+
+    assert!(window.context.is_some());
+}
+
+#[cfg(feature = "gl")]
+#[test]
+fn contexts_outlive_their_window() {
+    let window = xc::Window::new(c"win32").unwrap();
+    let shared = xc::GlContext::new(&window, window.context.as_ref()).unwrap();
+    assert!(shared.make_current(&window));
+
+    drop(window);
+    shared.make_not_current();
+}